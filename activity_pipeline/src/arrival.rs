@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use crate::rng::Rng;
+
+// Time between two consecutive batches, selected with "arrival" in "init-payload".
+// Time is virtual: the sum of the delays returned so far, so runs with the same seed are reproducible
+pub enum ArrivalProcess {
+    Constant {
+        interval_ms: u64,
+    },
+    Poisson {
+        mean_interval_ms: f64,
+    },
+    Bursty {
+        on_ms: u64,                 // Length of a burst
+        off_ms: u64,                // Silence between two bursts
+        interval_ms: u64,           // Time between two batches within a burst
+    },
+    Ramp {
+        start_rate_hz: f64,
+        end_rate_hz: f64,
+        duration_ms: u64,           // The rate stays at end_rate_hz afterwards
+    },
+}
+
+impl ArrivalProcess {
+    // Rejects the parameters for which the delays are undefined or null: bursts of no length or with no time
+    // between their batches, null or negative rates
+    pub fn from_arguments(arguments: &HashMap<&str, &str>, generation_interval_ms: u64) -> ArrivalProcess {
+        let parse = |key: &str, default: u64| -> u64 {
+            match arguments.get(key) {
+                Some(value) => value.parse::<u64>().unwrap_or_else(|_| panic!("Invalid {} provided", key)),
+                None => default,
+            }
+        };
+
+        match *arguments.get("arrival").unwrap_or(&"constant") {
+            "constant" => ArrivalProcess::Constant {
+                interval_ms: generation_interval_ms,
+            },
+            "poisson" => ArrivalProcess::Poisson {
+                mean_interval_ms: generation_interval_ms as f64,
+            },
+            "bursty" => {
+                let on_ms = parse("burst_on_ms", 10 * generation_interval_ms);
+                assert!(on_ms > 0, "The bursts must last at least 1 ms, provide a positive burst_on_ms");
+                let interval_ms = parse("burst_interval_ms", generation_interval_ms);
+                assert!(interval_ms > 0, "The batches of a burst must be at least 1 ms apart, provide a positive burst_interval_ms");
+                ArrivalProcess::Bursty {
+                    on_ms,
+                    off_ms: parse("burst_off_ms", 10 * generation_interval_ms),
+                    interval_ms,
+                }
+            }
+            "ramp" => {
+                let rate = |key: &str, default: &str| -> f64 {
+                    let rate_hz = arguments.get(key).unwrap_or(&default).parse::<f64>().unwrap_or_else(|_| panic!("Invalid {} provided", key));
+                    assert!(rate_hz.is_finite() && rate_hz > 0.0, "The {} must be a positive rate", key);
+                    rate_hz
+                };
+                ArrivalProcess::Ramp {
+                    start_rate_hz: rate("ramp_start_rate_hz", "0.2"),
+                    end_rate_hz: rate("ramp_end_rate_hz", "2"),
+                    duration_ms: parse("ramp_duration_ms", 300000),
+                }
+            }
+            other => panic!("Invalid arrival process provided: {}", other),
+        }
+    }
+
+    // Delay before the next batch, given the virtual time elapsed since the first one
+    pub fn next_delay_ms(&self, rng: &mut Rng, elapsed_ms: u64) -> u64 {
+        match self {
+            ArrivalProcess::Constant { interval_ms } => *interval_ms,
+            ArrivalProcess::Poisson { mean_interval_ms } => {
+                let u = (1.0 - rng.rand() as f64).max(f64::MIN_POSITIVE);      // (0, 1], keeps ln() finite even when a draw rounds up to 1
+                (-mean_interval_ms * u.ln()).round() as u64                     // Exponential inter-arrival time
+            }
+            ArrivalProcess::Bursty { on_ms, off_ms, interval_ms } => {
+                let period_ms = on_ms.saturating_add(*off_ms);
+                let phase_ms = elapsed_ms % period_ms;
+                if phase_ms.saturating_add(*interval_ms) < *on_ms {
+                    *interval_ms
+                } else {
+                    period_ms - phase_ms            // Wait for the start of the next burst
+                }
+            }
+            ArrivalProcess::Ramp { start_rate_hz, end_rate_hz, duration_ms } => {
+                let progress = (elapsed_ms as f64 / *duration_ms as f64).min(1.0);
+                let rate_hz = start_rate_hz + (end_rate_hz - start_rate_hz) * progress;
+                (1000.0 / rate_hz).round() as u64
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(arguments: &[(&str, &str)], generation_interval_ms: u64) -> ArrivalProcess {
+        ArrivalProcess::from_arguments(&arguments.iter().copied().collect(), generation_interval_ms)
+    }
+
+    // Delays of the first batches, the virtual time advancing by each of them
+    fn delays(process: &ArrivalProcess, count: usize) -> Vec<u64> {
+        let mut rng = Rng::new(42);
+        let mut elapsed_ms = 0;
+        (0..count)
+            .map(|_| {
+                let delay_ms = process.next_delay_ms(&mut rng, elapsed_ms);
+                elapsed_ms += delay_ms;
+                delay_ms
+            })
+            .collect()
+    }
+
+    #[test]
+    fn constant_keeps_the_generation_interval() {
+        assert_eq!(delays(&process(&[], 500), 5), vec![500; 5]);
+    }
+
+    #[test]
+    fn poisson_averages_the_generation_interval() {
+        let poisson = process(&[("arrival", "poisson")], 1000);
+        let intervals = delays(&poisson, 10000);
+        let mean_ms = intervals.iter().sum::<u64>() as f64 / intervals.len() as f64;
+        assert!((mean_ms - 1000.0).abs() < 50.0, "Mean interval of {} ms", mean_ms);
+        assert_eq!(intervals, delays(&poisson, 10000), "Same seed, different delays");
+    }
+
+    #[test]
+    fn bursty_waits_for_the_next_burst() {
+        let bursty = process(&[("arrival", "bursty"), ("burst_on_ms", "300"), ("burst_off_ms", "700")], 100);
+        assert_eq!(delays(&bursty, 8), vec![100, 100, 800, 100, 100, 800, 100, 100]);
+    }
+
+    #[test]
+    fn ramp_goes_from_the_start_rate_to_the_end_rate() {
+        let ramp = process(&[("arrival", "ramp"), ("ramp_start_rate_hz", "1"), ("ramp_end_rate_hz", "4"), ("ramp_duration_ms", "10000")], 1000);
+        let delays = delays(&ramp, 40);
+        assert_eq!(delays[0], 1000);
+        assert!(delays.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert_eq!(*delays.last().unwrap(), 250);
+    }
+
+    #[test]
+    #[should_panic(expected = "burst_on_ms")]
+    fn bursty_rejects_empty_bursts() {
+        process(&[("arrival", "bursty")], 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "burst_interval_ms")]
+    fn bursty_rejects_a_null_interval() {
+        process(&[("arrival", "bursty"), ("burst_on_ms", "300")], 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "ramp_start_rate_hz")]
+    fn ramp_rejects_a_null_rate() {
+        process(&[("arrival", "ramp"), ("ramp_start_rate_hz", "0")], 1000);
+    }
+}
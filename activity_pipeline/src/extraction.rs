@@ -0,0 +1,264 @@
+use std::collections::{HashMap, VecDeque};
+
+use edgeless_function::*;
+use serde::Serialize;
+
+use activity_schema::gravity::GravityFilter;
+use activity_schema::spectral::SpectralAnalyzer;
+use activity_schema::streaming::{FeatureExtractor, PercentileMode};
+use activity_schema::{ExtendedFeatures, FeatureGroups, Features, SpectralFeatures, NUM_CLASSES};
+
+use crate::{AccelerometerData, Classification};
+
+// Windowing and feature extraction of the samples of each device. Shared by extract_features and
+// by the fused function (functions/fused_activity_recognition), so that both compute the same features
+
+pub struct ExtractionConfig {       // Populated by what has been specified into "init-payload" in workflow.json
+    pub groups: FeatureGroups,                  // Optional feature groups computed on top of the time-domain ones
+    sample_rate_hz: f64,                        // Sampling rate of the received samples, for the jerk, the spectral features and the gravity filter
+    gravity_cutoff_hz: f64,                     // Cutoff of the gravity low-pass filter, when features are computed on the body acceleration
+    pub window_size: usize,                     // Samples of each window the features are computed over, as in classifier_training
+    stride: usize,                              // Samples between the starts of two consecutive windows of a device
+    percentile_mode: PercentileMode,
+}
+
+// Features of a complete window, along with its activity
+pub struct WindowFeatures {
+    pub true_class: Classification,
+    pub features: Features,
+    pub extended_features: Option<ExtendedFeatures>,
+    pub spectral_features: Option<SpectralFeatures>,
+}
+
+// Samples of a device received since its last complete window, along with their activity
+#[derive(Default)]
+struct DeviceWindow {
+    samples: VecDeque<AccelerometerData>,
+    classes: VecDeque<Classification>,
+    next_sequence: u64,                 // Sequence number following that of the last sample received
+}
+
+// Sent to "partial_windows" for every batch that completed no window
+#[derive(Serialize)]
+struct PartialWindow {
+    batch_id: u64,
+    device_id: u32,
+    pending_samples: usize,             // Samples of the device buffered so far
+    window_size: usize,
+}
+
+pub struct Extraction {
+    pub config: ExtractionConfig,
+    extractor: FeatureExtractor,
+    spectral: SpectralAnalyzer,                         // FFT plan and buffers for the window size, reused by every window
+    signal: Vec<f64>,                                   // Scratch signal of the spectral features
+    windows: HashMap<u32, DeviceWindow>,                // Per device
+    gravity_filters: HashMap<u32, GravityFilter>,       // Per device
+}
+
+impl ExtractionConfig {
+    pub fn from_arguments(arguments: &HashMap<&str, &str>) -> ExtractionConfig {
+        let extended = arguments.get("extended_features").unwrap_or(&"false").parse::<bool>().expect("Invalid extended features flag provided");
+        let spectral = arguments.get("spectral_features").unwrap_or(&"false").parse::<bool>().expect("Invalid spectral features flag provided");
+        let sample_rate_hz = arguments.get("sample_rate_hz").unwrap_or(&"20").parse::<f64>().expect("Invalid sample rate provided");
+        let body = arguments.get("body_acceleration").unwrap_or(&"false").parse::<bool>().expect("Invalid body acceleration flag provided");
+        let gravity_cutoff_hz = match arguments.get("gravity_cutoff_hz") {
+            Some(value) => value.parse::<f64>().expect("Invalid gravity cutoff provided"),
+            None => activity_schema::gravity::GRAVITY_CUTOFF_HZ,
+        };
+        let window_size = arguments.get("window_size").unwrap_or(&"100").parse::<usize>().expect("Invalid window size provided");
+        let stride = arguments.get("stride").map_or(window_size, |value| value.parse::<usize>().expect("Invalid stride provided"));
+        assert!(window_size > 0 && stride > 0 && stride <= window_size, "The stride must be between 1 and the window size");
+        let percentiles = arguments.get("percentiles").unwrap_or(&"exact");
+        let percentile_mode = PercentileMode::from_name(percentiles).expect("Invalid percentiles mode provided, expected exact or p2");
+
+        ExtractionConfig {
+            groups: FeatureGroups { extended, spectral, body },
+            sample_rate_hz,
+            gravity_cutoff_hz,
+            window_size,
+            stride,
+            percentile_mode,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "window size: {}, stride: {}, extended features: {}, spectral features: {}, body acceleration: {}, percentiles: {:?}",
+            self.window_size, self.stride, self.groups.extended, self.groups.spectral, self.groups.body, self.percentile_mode
+        )
+    }
+}
+
+impl Extraction {
+    pub fn new(config: ExtractionConfig) -> Extraction {
+        Extraction {
+            extractor: FeatureExtractor::new(config.percentile_mode),
+            spectral: SpectralAnalyzer::new(),
+            signal: Vec::with_capacity(config.window_size),
+            config,
+            windows: HashMap::new(),
+            gravity_filters: HashMap::new(),
+        }
+    }
+
+    pub fn schema_hash(&self) -> u64 {
+        activity_schema::schema_hash(self.config.groups)
+    }
+
+    // Samples of a device received since its last complete window
+    pub fn pending(&self, device_id: u32) -> usize {
+        self.windows.get(&device_id).map_or(0, |window| window.samples.len())
+    }
+
+    // Casts a batch that completed no window to "partial_windows"
+    pub fn report_partial(&self, batch_id: u64, device_id: u32) {
+        let record = PartialWindow {
+            batch_id,
+            device_id,
+            pending_samples: self.pending(device_id),
+            window_size: self.config.window_size,
+        };
+        log::info!(
+            "Batch #{}: partial window of device #{} ({}/{} samples), not classified",
+            batch_id,
+            device_id,
+            record.pending_samples,
+            record.window_size
+        );
+        match serde_json::to_string(&record) {
+            Ok(json) => cast("partial_windows", json.as_bytes()),
+            Err(e) => log::info!("Error serializing partial window: {}", e),
+        }
+    }
+
+    // Adds a batch of a device to its window and returns the features of every window it completed.
+    // Samples already received with the previous batch of the device are skipped: those numbered below
+    // the next expected one when `sequences` numbers the samples, else the first `overlap` ones
+    pub fn push(
+        &mut self,
+        device_id: u32,
+        true_class: Classification,
+        mut accelerometer_data: Vec<AccelerometerData>,
+        sequences: Option<&[u64]>,
+        overlap: usize,
+    ) -> Vec<WindowFeatures> {
+        let config = &self.config;
+        let window = self.windows.entry(device_id).or_default();
+
+        // Numbered samples stay aligned whatever fault injection dropped, in the overlap or not
+        let received = match sequences {
+            Some(sequences) => {
+                let received = sequences.iter().take_while(|sequence| **sequence < window.next_sequence).count();
+                if let Some(last_sequence) = sequences.last() {
+                    window.next_sequence = window.next_sequence.max(last_sequence + 1);
+                }
+                received
+            }
+            None => overlap.min(accelerometer_data.len()),
+        };
+
+        // Gravity removed with the filter state left by the previous batch of the device
+        if config.groups.body {
+            self.gravity_filters
+                .entry(device_id)
+                .or_insert_with(|| GravityFilter::new(config.gravity_cutoff_hz, config.sample_rate_hz))
+                .separate(&mut accelerometer_data, received);
+        }
+
+        // Only the samples that did not come with the previous batch of the device join its window
+        let new_samples = &accelerometer_data[received..];
+        window.samples.extend(new_samples.iter().cloned());
+        window.classes.extend(std::iter::repeat_n(true_class, new_samples.len()));
+
+        let mut completed = Vec::new();
+        while window.samples.len() >= config.window_size {
+            let samples = &window.samples.make_contiguous()[..config.window_size];
+            let window_class = majority_class(&window.classes.make_contiguous()[..config.window_size]);
+
+            let extended_features = if config.groups.extended {
+                Some(activity_schema::extended::extended_features(samples, config.sample_rate_hz))
+            } else {
+                None
+            };
+            let spectral_features = if config.groups.spectral {
+                Some(spectral_features(&mut self.spectral, &mut self.signal, samples, config.sample_rate_hz))
+            } else {
+                None
+            };
+
+            // Single pass over the window, reusing the buffers of the previous windows
+            let features = self.extractor.extract(samples);
+
+            completed.push(WindowFeatures {
+                true_class: window_class,
+                features,
+                extended_features,
+                spectral_features,
+            });
+
+            window.samples.drain(..config.stride);
+            window.classes.drain(..config.stride);
+        }
+
+        completed
+    }
+}
+
+// Most frequent activity among the samples of a window, used as its ground truth. Unknown, which
+// only classify produces, is not counted
+fn majority_class(classes: &[Classification]) -> Classification {
+    let mut counts = [0usize; NUM_CLASSES];
+    for class in classes {
+        if let Some(count) = counts.get_mut(class.label() as usize) {
+            *count += 1;
+        }
+    }
+    Classification::ALL
+        .into_iter()
+        .max_by_key(|class| counts[class.label() as usize])
+        .unwrap()
+}
+
+// Spectral feature group of the magnitude and of each axis, each signal written to the same scratch buffer
+fn spectral_features(analyzer: &mut SpectralAnalyzer, values: &mut Vec<f64>, accelerometer_data: &[AccelerometerData], sample_rate_hz: f64) -> SpectralFeatures {
+    let mut signal = |value: fn(&AccelerometerData) -> f64| -> activity_schema::SpectralStats {
+        values.clear();
+        values.extend(accelerometer_data.iter().map(value));
+        analyzer.stats(values, sample_rate_hz)
+    };
+
+    let magnitude = signal(|data| ((data.x.powi(2)) + (data.y.powi(2)) + (data.z.powi(2))).sqrt());
+    let x = signal(|data| data.x);
+    let y = signal(|data| data.y);
+    let z = signal(|data| data.z);
+    SpectralFeatures::from_signals(magnitude, x, y, z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(sequences: &[u64]) -> Vec<AccelerometerData> {
+        sequences.iter().map(|sequence| AccelerometerData { x: *sequence as f64, y: 0.0, z: 9.8 }).collect()
+    }
+
+    // A sample dropped within the overlap shifts the rest of the batch: counting `overlap` samples
+    // would skip one that has not been received yet, the sequence numbers do not
+    #[test]
+    fn dropped_overlap_samples_keep_the_windows_aligned() {
+        let arguments = HashMap::from([("window_size", "4"), ("stride", "2")]);
+        let mut extraction = Extraction::new(ExtractionConfig::from_arguments(&arguments));
+
+        let first = extraction.push(0, Classification::Walking, samples(&[0, 1, 2, 3]), Some(&[0, 1, 2, 3]), 0);
+        assert_eq!(first.len(), 1);
+
+        // Window [2, 3, 4, 5] with its first sample dropped
+        let sequences = activity_schema::sample_sequences(2, &[0], 3);
+        assert_eq!(sequences, vec![3, 4, 5]);
+        let second = extraction.push(0, Classification::Walking, samples(&sequences), Some(&sequences), 2);
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].features.mean_x, 3.5);
+        assert_eq!(extraction.pending(0), 2);
+    }
+}
@@ -0,0 +1,204 @@
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+
+use crate::rng::Rng;
+use crate::AccelerometerData;
+
+// Sensor faults applied to each emitted batch, configured with the "fault_*" keys of "init-payload".
+// Probabilities are per sample for dropped and non-finite readings, per batch for the others
+pub struct FaultConfig {
+    after_batch: u64,                   // Faults are injected from this batch of the device on
+    drop_prob: f64,
+    nan_prob: f64,
+    inf_prob: f64,
+    stuck_prob: f64,
+    stuck_len: usize,                   // Consecutive samples repeating the same reading
+    clip_prob: f64,
+    clip_level: f64,                    // Saturation level of a clipping sensor
+    swap_prob: f64,
+    drift_per_sample: f64,              // Bias added to the x axis at every sample, accumulated over the run
+}
+
+impl FaultConfig {
+    // None if no fault has been configured
+    pub fn from_arguments(arguments: &HashMap<&str, &str>) -> Option<FaultConfig> {
+        let parse = |key: &str, default: f64| -> f64 {
+            match arguments.get(key) {
+                Some(value) => value.parse::<f64>().unwrap_or_else(|_| panic!("Invalid {} provided", key)),
+                None => default,
+            }
+        };
+
+        let config = FaultConfig {
+            after_batch: parse("fault_after_batch", 0.0) as u64,
+            drop_prob: parse("fault_drop_prob", 0.0),
+            nan_prob: parse("fault_nan_prob", 0.0),
+            inf_prob: parse("fault_inf_prob", 0.0),
+            stuck_prob: parse("fault_stuck_prob", 0.0),
+            stuck_len: parse("fault_stuck_len", 20.0) as usize,
+            clip_prob: parse("fault_clip_prob", 0.0),
+            clip_level: parse("fault_clip_level", 9.8),
+            swap_prob: parse("fault_swap_prob", 0.0),
+            drift_per_sample: parse("fault_drift_per_sample", 0.0),
+        };
+
+        let any_fault = config.drop_prob > 0.0
+            || config.nan_prob > 0.0
+            || config.inf_prob > 0.0
+            || config.stuck_prob > 0.0
+            || config.clip_prob > 0.0
+            || config.swap_prob > 0.0
+            || config.drift_per_sample != 0.0;
+
+        if any_fault { Some(config) } else { None }
+    }
+}
+
+// Per-device fault state
+#[derive(Default, Serialize, Deserialize)]
+pub struct FaultInjector {
+    drift: f64,
+}
+
+impl FaultInjector {
+    // Corrupts the batch in place and returns the names of the injected faults, along with the positions
+    // in the batch of the samples it dropped
+    pub fn inject(&mut self, config: &FaultConfig, rng: &mut Rng, device_batch: u64, batch: &mut Vec<AccelerometerData>) -> (Vec<&'static str>, Vec<u32>) {
+        let mut faults = Vec::new();
+        let mut dropped = Vec::new();
+        if device_batch < config.after_batch {
+            return (faults, dropped);
+        }
+
+        if config.drift_per_sample != 0.0 {
+            for sample in batch.iter_mut() {
+                self.drift += config.drift_per_sample;
+                sample.x += self.drift;
+            }
+            faults.push("bias_drift");
+        }
+
+        if config.swap_prob > 0.0 && (rng.rand() as f64) < config.swap_prob {
+            for sample in batch.iter_mut() {
+                std::mem::swap(&mut sample.x, &mut sample.y);
+            }
+            faults.push("axis_swap");
+        }
+
+        if config.clip_prob > 0.0 && (rng.rand() as f64) < config.clip_prob {
+            for sample in batch.iter_mut() {
+                sample.x = sample.x.clamp(-config.clip_level, config.clip_level);
+                sample.y = sample.y.clamp(-config.clip_level, config.clip_level);
+                sample.z = sample.z.clamp(-config.clip_level, config.clip_level);
+            }
+            faults.push("clipping");
+        }
+
+        if config.stuck_prob > 0.0 && !batch.is_empty() && (rng.rand() as f64) < config.stuck_prob {
+            let start = (rng.rand() as f64 * batch.len() as f64) as usize % batch.len();
+            let stuck_reading = batch[start].clone();
+            for sample in batch.iter_mut().skip(start).take(config.stuck_len) {
+                *sample = stuck_reading.clone();
+            }
+            faults.push("stuck_at");
+        }
+
+        if config.nan_prob > 0.0 || config.inf_prob > 0.0 {
+            let mut non_finite = false;
+            for sample in batch.iter_mut() {
+                if config.nan_prob > 0.0 && (rng.rand() as f64) < config.nan_prob {
+                    sample.x = f64::NAN;
+                    non_finite = true;
+                }
+                if config.inf_prob > 0.0 && (rng.rand() as f64) < config.inf_prob {
+                    sample.z = f64::INFINITY;
+                    non_finite = true;
+                }
+            }
+            if non_finite {
+                faults.push("non_finite");
+            }
+        }
+
+        if config.drop_prob > 0.0 {
+            let mut position = 0;
+            batch.retain(|_| {
+                let kept = (rng.rand() as f64) >= config.drop_prob;
+                if !kept {
+                    dropped.push(position);
+                }
+                position += 1;
+                kept
+            });
+            if !dropped.is_empty() {
+                faults.push("dropped_samples");
+            }
+        }
+
+        (faults, dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(arguments: &[(&str, &str)]) -> FaultConfig {
+        FaultConfig::from_arguments(&arguments.iter().copied().collect()).expect("No fault configured")
+    }
+
+    fn batch(len: usize) -> Vec<AccelerometerData> {
+        (0..len).map(|i| AccelerometerData { x: i as f64, y: -20.0, z: 20.0 }).collect()
+    }
+
+    #[test]
+    fn no_fault_keys_disable_injection() {
+        assert!(FaultConfig::from_arguments(&HashMap::new()).is_none());
+        assert!(FaultConfig::from_arguments(&HashMap::from([("fault_stuck_len", "5")])).is_none());
+    }
+
+    #[test]
+    fn faults_start_after_the_configured_batch() {
+        let config = config(&[("fault_clip_prob", "1"), ("fault_after_batch", "2")]);
+        let mut injector = FaultInjector::default();
+        let mut rng = Rng::new(0);
+
+        let mut samples = batch(10);
+        assert!(injector.inject(&config, &mut rng, 1, &mut samples).0.is_empty());
+        assert_eq!(samples[0].y, -20.0);
+
+        let (faults, dropped) = injector.inject(&config, &mut rng, 2, &mut samples);
+        assert_eq!(faults, vec!["clipping"]);
+        assert!(dropped.is_empty());
+        assert!(samples.iter().all(|sample| sample.x.abs() <= 9.8 && sample.y == -9.8 && sample.z == 9.8));
+    }
+
+    #[test]
+    fn dropped_positions_are_those_missing_from_the_batch() {
+        let config = config(&[("fault_drop_prob", "0.3")]);
+        let mut samples = batch(200);
+        let (faults, dropped) = FaultInjector::default().inject(&config, &mut Rng::new(7), 0, &mut samples);
+
+        assert_eq!(faults, vec!["dropped_samples"]);
+        assert_eq!(samples.len() + dropped.len(), 200);
+        // The x axis holds the original position of each sample
+        let kept: Vec<u32> = samples.iter().map(|sample| sample.x as u32).collect();
+        assert!((0..200).all(|position| kept.contains(&position) != dropped.contains(&position)));
+    }
+
+    #[test]
+    fn drift_accumulates_across_batches() {
+        let config = config(&[("fault_drift_per_sample", "0.5")]);
+        let mut injector = FaultInjector::default();
+        let mut rng = Rng::new(0);
+
+        let mut first = batch(4);
+        injector.inject(&config, &mut rng, 0, &mut first);
+        let mut second = batch(4);
+        let (faults, _) = injector.inject(&config, &mut rng, 1, &mut second);
+
+        assert_eq!(faults, vec!["bias_drift"]);
+        assert_eq!(first.iter().map(|sample| sample.x).collect::<Vec<f64>>(), vec![0.5, 2.0, 3.5, 5.0]);
+        assert_eq!(second.iter().map(|sample| sample.x).collect::<Vec<f64>>(), vec![2.5, 4.0, 5.5, 7.0]);
+    }
+}
@@ -0,0 +1,356 @@
+use edgeless_function::*;
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+
+use crate::{arrival, faults, replay, rng, scenario, synced, synthetic, tables};
+use crate::{AccelerometerData, Classification};
+
+// Emulated devices and the batches they generate, shared by generate_samples and by the fused
+// function (functions/fused_activity_recognition), which only differ in what they do with each batch
+
+pub struct GeneratorConfig {        // Populated by what has been specified into "init-payload" in workflow.json
+    batch_size: u64,                            // Length of each activity segment drawn from the source
+    window_size: usize,                         // Samples in each emitted batch
+    stride: usize,                              // Samples between the starts of two consecutive batches
+    arrival: arrival::ArrivalProcess,           // Time between two batches
+    max_batches: Option<u64>,                   // Each device stops after this many batches
+    run_duration_ms: Option<u64>,               // Each device stops once its next batch would fall after this (virtual) time
+    faults: Option<faults::FaultConfig>,        // Sensor faults to inject, if any
+}
+
+// Synced through EDGELESS state sync after every batch, as the "generator" section (see synced), and
+// restored on re-init, so that a relocated instance continues the run where the previous one stopped
+#[derive(Serialize, Deserialize)]
+pub struct State {
+    counter: u64,                               // Batch ids are unique across devices
+    devices: Vec<Device>,
+    first_batch_id: u64,
+    finished: bool,                             // Set once the end-of-run record has been sent
+}
+
+// An emulated wearable, scheduling its own batches independently of the others
+#[derive(Serialize, Deserialize)]
+struct Device {
+    device_id: u32,
+    batches: u64,                               // Batches generated by this device
+    elapsed_ms: u64,                            // Virtual time since the first batch, drives the arrival process
+    rng: rng::Rng,                              // Random values generation, persisted across migrations
+    classes: scenario::ClassSequence,           // Activity of each segment, ignored when replaying a trace
+    source: SampleSource,
+    stream: std::collections::VecDeque<(Classification, AccelerometerData)>,       // Samples not yet emitted, or shared with the next window
+    stream_start: u64,                          // Sequence number of the first sample of the stream
+    fault_injector: faults::FaultInjector,
+    pending_delay_ms: u64,                      // Delay of the batch currently scheduled
+    finished: bool,
+}
+
+// Where the samples of each batch come from, selected with "mode" in "init-payload"
+#[derive(Serialize, Deserialize)]
+enum SampleSource {
+    Tables,                                     // Hard-coded 100-sample tables
+    Synthetic(synthetic::SyntheticGenerator),   // Parametric signal model, any batch size
+    Replay(replay::TraceReplay),                // Recorded trace behind the sqlx resource
+}
+
+// Sent to "ttc_measurement_start" after the last batch of a finite run
+#[derive(Debug, Serialize)]
+struct EndOfRunRecord {
+    total_batches: u64,
+    first_batch_id: u64,
+    last_batch_id: Option<u64>,                 // None if the run ended before generating any batch
+}
+
+// A window of samples generated by a device
+pub struct Batch {
+    pub batch_id: u64,
+    pub device_id: u32,
+    pub true_class: Classification,             // Most frequent activity among the samples
+    pub samples: Vec<AccelerometerData>,
+    pub overlap: usize,                         // Leading samples shared with the device's previous batch
+    pub first_sequence: u64,                    // Sequence number of the first sample of the window in the device's stream
+    pub dropped: Vec<u32>,                      // Positions in the window of the samples dropped by fault injection
+    pub faults: Vec<&'static str>,              // Sensor faults injected in this batch
+}
+
+// Parses the generation parameters, then restores the synced state if the function has been relocated
+// or creates the devices otherwise. Each device schedules itself with its id as message
+pub fn init(arguments: &HashMap<&str, &str>, synced_state: Option<&[u8]>) -> (GeneratorConfig, State) {
+    let batch_size = arguments.get("batch_size").expect("Invalid batch size provided").parse::<u64>().unwrap();
+    assert!(batch_size > 0, "The batch size must be at least 1");          // Empty segments would never fill a window
+    let generation_interval_ms = arguments.get("generation_interval_ms").expect("Invalid generation interval provided").parse::<u64>().unwrap();
+    let start_working_after_ms = arguments.get("start_working_after_ms").expect("Invalid starting delay provided").parse::<u64>().unwrap();
+    let seed = arguments.get("seed").unwrap_or(&"0").parse::<u32>().unwrap_or(0);
+    let window_size = arguments.get("window_size").map_or(batch_size as usize, |value| value.parse::<usize>().expect("Invalid window size provided"));
+    let stride = arguments.get("stride").map_or(window_size, |value| value.parse::<usize>().expect("Invalid stride provided"));
+    assert!(window_size > 0 && stride > 0 && stride <= window_size, "The stride must be between 1 and the window size");
+    let max_batches = arguments.get("max_batches").map(|value| value.parse::<u64>().expect("Invalid maximum number of batches provided"));
+    let run_duration_ms = arguments.get("run_duration_ms").map(|value| value.parse::<u64>().expect("Invalid run duration provided"));
+    let num_devices = arguments.get("num_devices").unwrap_or(&"1").parse::<u32>().expect("Invalid number of devices provided");
+    assert!(num_devices > 0, "At least one device must be emulated");
+
+    let sample_rate_hz = arguments.get("sample_rate_hz").unwrap_or(&"20").parse::<f64>().expect("Invalid sample rate provided");
+    let classes = scenario::ClassSequence::from_arguments(arguments, sample_rate_hz);
+
+    let mode = *arguments.get("mode").unwrap_or(&"tables");
+    let new_source = || match mode {
+        "tables" => {
+            if batch_size > 100 {
+                log::warn!(
+                    "The sample tables hold 100 samples: activity segments are cut to 100 samples, so a window of {} samples is filled from several tables, possibly of different activities (use mode=synthetic)",
+                    window_size
+                );
+            }
+            SampleSource::Tables
+        }
+        "synthetic" => {
+            SampleSource::Synthetic(synthetic::SyntheticGenerator::new(sample_rate_hz))
+        }
+        "replay" => {
+            let trace_id = arguments.get("trace_id").expect("No trace id provided for replay mode");
+            let loop_trace = arguments.get("replay_loop").unwrap_or(&"true").parse::<bool>().unwrap_or(true);
+            log::info!("Replaying trace '{}' (loop: {})", trace_id, loop_trace);
+            SampleSource::Replay(replay::TraceReplay::new(trace_id, loop_trace))
+        }
+        other => panic!("Invalid generation mode provided: {}", other),
+    };
+
+    // Device 0 uses the configured seed as is, so a single device behaves as a standalone generator
+    let devices: Vec<Device> = (0..num_devices)
+        .map(|device_id| Device {
+            device_id,
+            batches: 0,
+            elapsed_ms: 0,
+            rng: rng::Rng::new(seed.wrapping_add(device_id.wrapping_mul(7919))),
+            classes: classes.clone(),
+            source: new_source(),
+            stream: std::collections::VecDeque::new(),
+            stream_start: 0,
+            fault_injector: faults::FaultInjector::default(),
+            pending_delay_ms: generation_interval_ms * device_id as u64 / num_devices as u64,
+            finished: false,
+        })
+        .collect();
+
+    let config = GeneratorConfig {
+        batch_size,
+        window_size,
+        stride,
+        arrival: arrival::ArrivalProcess::from_arguments(arguments, generation_interval_ms),
+        max_batches,
+        run_duration_ms,
+        faults: faults::FaultConfig::from_arguments(arguments),
+    };
+
+    // A state synced by a previous instance means that the function has been relocated
+    if let Some(synced_state) = synced::restore(synced_state, "generator").and_then(|synced_state| serde_json::from_slice::<State>(&synced_state).ok()) {
+        log::info!("Restored state after relocation, continuing from batch #{}", synced_state.counter);
+
+        for device in synced_state.devices.iter().filter(|device| !device.finished) {
+            delayed_cast(device.pending_delay_ms, "self", device.device_id.to_string().as_bytes());
+        }
+
+        return (config, synced_state);
+    }
+
+    let state = State {
+        counter: 0,
+        devices,
+        first_batch_id: 0,
+        finished: false,
+    };
+
+    log::info!(
+        "Starting in {} ms... Devices: {}, Batch size: {}, Window size: {}, Stride: {}, Generation interval (ms): {}, Arrival process: {}",
+        start_working_after_ms,
+        num_devices,
+        batch_size,
+        window_size,
+        stride,
+        generation_interval_ms,
+        arguments.get("arrival").unwrap_or(&"constant")
+    );
+
+    // Staggering the devices' first batches over one generation interval, so that they do not fire in lockstep
+    for device in state.devices.iter() {
+        delayed_cast(start_working_after_ms + device.pending_delay_ms, "self", device.device_id.to_string().as_bytes());          // Action happens in handle_cast()
+    }
+
+    (config, state)
+}
+
+// Generates the next batch of a device, hands it to `emit` and schedules the device's following batch
+pub fn generate(config: &GeneratorConfig, state: &mut State, device_id: usize, emit: impl FnOnce(Batch)) {
+    if state.finished {
+        return;
+    }
+
+    let device = match state.devices.get_mut(device_id) {
+        Some(device) if !device.finished => device,
+        _ => return,
+    };
+
+    // Extending the continuous sample stream until it holds a full window
+    while device.stream.len() < config.window_size {
+        match next_segment(device, config.batch_size as usize) {
+            Ok(Some(segment)) => device.stream.extend(segment),
+            Err(e) => {
+                // Transient, e.g. the database not reachable: no batch this time, the device tries again at its next one
+                log::info!("No batch generated by device #{}: {}", device.device_id, e);
+                schedule_next(config, state, device_id);
+                return;
+            }
+            Ok(None) => {
+                log::info!("Trace replay over for device #{}, no more batches will be generated", device.device_id);
+                device.finished = true;
+                if state.devices.iter().all(|device| device.finished) {
+                    end_run(state);
+                }
+                persist(state);
+                return;
+            }
+        }
+    }
+
+    let chosen_class = majority_class(device.stream.iter().take(config.window_size).map(|(class, _)| *class));
+    let mut batch: Vec<AccelerometerData> = device.stream
+        .iter()
+        .take(config.window_size)
+        .map(|(_, sample)| sample.clone())
+        .collect();
+
+    let first_sequence = device.stream_start;
+    device.stream.drain(..config.stride);           // Consecutive windows overlap by window_size - stride samples
+    device.stream_start += config.stride as u64;
+    let overlap = if device.batches == 0 { 0 } else { config.window_size - config.stride };
+
+    let (faults, dropped) = match &config.faults {
+        Some(fault_config) => device.fault_injector.inject(fault_config, &mut device.rng, device.batches, &mut batch),
+        None => (Vec::new(), Vec::new()),
+    };
+    if !faults.is_empty() {
+        log::info!("Injected faults in batch #{}: {:?}", state.counter, faults);
+    }
+
+    if state.counter == 0 {
+        cast("ttc_measurement_start", "--- NEW MEASUREMENT START ---".as_bytes());
+    }
+
+    log::info!("Generated batch #{} (device #{}, batch #{} of the device)", state.counter, device.device_id, device.batches);
+
+    // Logging to file-log resource
+    cast("ttc_measurement_start", format!("{}", state.counter).as_bytes());
+
+    emit(Batch {
+        batch_id: state.counter,
+        device_id: device.device_id,
+        true_class: chosen_class,
+        samples: batch,
+        overlap,
+        first_sequence,
+        dropped,
+        faults,
+    });
+
+    state.counter += 1;
+    device.batches += 1;
+
+    schedule_next(config, state, device_id);
+}
+
+// Schedules the next batch of a device, or stops the device once it reached the end of the run
+fn schedule_next(config: &GeneratorConfig, state: &mut State, device_id: usize) {
+    let device = &mut state.devices[device_id];
+    let next_delay_ms = config.arrival.next_delay_ms(&mut device.rng, device.elapsed_ms);
+    let batches_reached = config.max_batches.is_some_and(|max_batches| device.batches >= max_batches);
+    let duration_reached = config.run_duration_ms.is_some_and(|run_duration_ms| device.elapsed_ms.saturating_add(next_delay_ms) > run_duration_ms);
+
+    if batches_reached || duration_reached {
+        device.finished = true;
+        if state.devices.iter().all(|device| device.finished) {
+            end_run(state);
+        }
+        persist(state);
+        return;
+    }
+
+    device.elapsed_ms = device.elapsed_ms.saturating_add(next_delay_ms);
+    device.pending_delay_ms = next_delay_ms;
+    delayed_cast(next_delay_ms, "self", device.device_id.to_string().as_bytes());
+
+    persist(state);
+}
+
+// Draws the next activity segment from the configured source, None once the source is over. A segment
+// holds up to batch_size samples of a single activity, fewer where a scenario step ends
+fn next_segment(device: &mut Device, batch_size: usize) -> Result<Option<Vec<(Classification, AccelerometerData)>>, String> {
+    match &mut device.source {
+        SampleSource::Replay(trace_replay) => trace_replay.next_samples(batch_size),
+        SampleSource::Synthetic(generator) => {
+            let (chosen_class, segment_len) = device.classes.next_class(&mut device.rng, batch_size);
+
+            let batch = generator.next_batch(&mut device.rng, chosen_class, segment_len);
+            Ok(Some(batch.into_iter().map(|sample| (chosen_class, sample)).collect()))
+        }
+        SampleSource::Tables => {
+            let accelerometer_samples_per_table = 100;
+            let (chosen_class, segment_len) = device.classes.next_class(&mut device.rng, batch_size.min(accelerometer_samples_per_table));
+            let accelerometer_samples = tables::get_accelerometer_samples(chosen_class);
+
+            let mut batch = Vec::with_capacity(segment_len);
+
+            for sample in accelerometer_samples.iter().take(segment_len) {                // Generating batch of accelerometric data
+                batch.push((
+                    chosen_class,
+                    AccelerometerData {
+                        x: sample[0],
+                        y: sample[1],
+                        z: sample[2],
+                    }
+                ));
+            }
+
+            Ok(Some(batch))
+        }
+    }
+}
+
+// Most frequent activity among the samples of a window, used as its ground truth. Unknown, which
+// only classify produces, is not counted
+fn majority_class(classes: impl Iterator<Item = Classification>) -> Classification {
+    let mut votes = [0usize; activity_schema::NUM_CLASSES];
+    for class in classes {
+        if let Some(vote) = votes.get_mut(class as usize) {
+            *vote += 1;
+        }
+    }
+
+    let majority_label = (0..votes.len()).max_by_key(|&label| votes[label]).unwrap();
+    Classification::from_label(majority_label as u32).unwrap()
+}
+
+// Hands the state over to EDGELESS state sync
+fn persist(state: &State) {
+    match serde_json::to_vec(state) {
+        Ok(serialized_state) => synced::persist("generator", serialized_state),
+        Err(e) => log::info!("Error serializing state: {}", e),
+    }
+}
+
+// Stops self-scheduling and marks the end of the run in the TTC log
+fn end_run(state: &mut State) {
+    state.finished = true;
+
+    let total_batches = state.counter - state.first_batch_id;
+    let record = EndOfRunRecord {
+        total_batches,
+        first_batch_id: state.first_batch_id,
+        last_batch_id: if total_batches > 0 { Some(state.counter - 1) } else { None },
+    };
+
+    match serde_json::to_string(&record) {
+        Ok(json) => cast("ttc_measurement_start", format!("--- END OF RUN --- {}", json).as_bytes()),
+        Err(e) => log::info!("Error serializing end-of-run record: {}", e),
+    }
+
+    log::info!("Run over after {} batches", total_batches);
+}
@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use edgeless_function::*;
+use activity_schema::timing::{self, HopEvent};
+use activity_schema::HopTimestamps;
+
+// Per-hop latency instrumentation (see activity_schema::timing), enabled with "hop_timing=true" in the
+// init-payload of every function of the workflow. Off by default: the hop marks are two more casts per
+// stage and the in-band stamps grow every payload, both counting in the TTC, the energy and the payload
+// sizes being measured
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HopTiming {
+    enabled: bool,
+}
+
+impl HopTiming {
+    pub fn from_arguments(arguments: &HashMap<&str, &str>) -> HopTiming {
+        let enabled = arguments.get("hop_timing").unwrap_or(&"false").parse::<bool>().expect("Invalid hop timing flag provided");
+        HopTiming { enabled }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    // Receive time of a payload, None when disabled or where the target has no clock
+    pub fn received_us(&self) -> Option<u64> {
+        if self.enabled {
+            timing::now_us()
+        } else {
+            None
+        }
+    }
+
+    // Appends the in-band stamps of a stage to the hops of its payload. Nothing is appended when
+    // disabled or where the target has no clock, rather than stamps left empty
+    pub fn stamp(&self, hops: &mut Vec<HopTimestamps>, stage: &str, received_us: Option<u64>) {
+        if !self.enabled {
+            return;
+        }
+        if let Some(sent_us) = timing::now_us() {
+            hops.push(HopTimestamps { stage: stage.to_string(), received_us, sent_us: Some(sent_us) });
+        }
+    }
+
+    // Casts a hop mark to "hop_log", stamped by the node hosting the file-log resource
+    pub fn mark(&self, id: impl Display, stage: &str, event: HopEvent) {
+        if self.enabled {
+            cast("hop_log", timing::hop_mark(id, stage, event).as_bytes());
+        }
+    }
+}
@@ -0,0 +1,30 @@
+// Logic of the stages of the activity recognition workflow, shared by the split functions and by
+// fused_activity_recognition, which runs all of them within a single function. Each function enables
+// the features of the stages it runs, and keeps to itself what only concerns its EDGELESS side
+// (payloads and outputs)
+pub use activity_schema::{AccelerometerData, Classification, HopTimestamps, WindowId, NUM_CLASSES};
+
+#[cfg(feature = "generator")]
+mod arrival;
+#[cfg(feature = "extraction")]
+pub mod extraction;
+#[cfg(feature = "generator")]
+mod faults;
+#[cfg(feature = "generator")]
+pub mod generator;
+pub mod hops;
+#[cfg(feature = "model")]
+pub mod model;
+#[cfg(feature = "generator")]
+mod replay;
+#[cfg(feature = "report")]
+pub mod report;
+#[cfg(feature = "generator")]
+mod rng;
+#[cfg(feature = "generator")]
+mod scenario;
+pub mod synced;
+#[cfg(feature = "generator")]
+mod synthetic;
+#[cfg(feature = "generator")]
+mod tables;
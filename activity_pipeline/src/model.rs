@@ -0,0 +1,380 @@
+use base64::Engine;
+use edgeless_function::*;
+use serde::{Deserialize, Serialize};
+use smartcore::linalg::basic::matrix::DenseMatrix;
+use std::collections::{HashMap, VecDeque};
+use activity_schema::models::{Classifier, ModelKind};
+
+use crate::{synced, Classification, NUM_CLASSES};
+
+// Classifier stored by classifier_training behind the sqlx resource, of any of the families of
+// activity_schema::models. Shared by classify and by the fused function (functions/fused_activity_recognition),
+// which load it the same way
+
+pub struct Model {
+    classifier: Box<dyn Classifier>,
+    schema_hash: Option<u64>,           // Feature schema the model has been trained on, unknown for older models
+    pub version: Option<u64>,           // Set by classifier_training, unknown for older models
+}
+
+// Class of a sample along with the votes it got
+pub struct Prediction {
+    pub classification: Classification,         // Unknown if the winning class got less than the confidence threshold
+    pub class_votes: [f64; NUM_CLASSES],        // Fraction of the votes each class got, indexed by label
+}
+
+// Row returned by the sqlx resource, with the metadata of a classifier or only its version
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+struct SqlxRow<M> {
+    id: String,
+    metadata: M,
+}
+
+// Metadata to hold the classifier as base64 string
+#[derive(serde::Serialize, serde::Deserialize, Default, Debug)]
+struct ClassifierData {
+    #[serde(default)]
+    model_id: Option<String>,  // Unset for models stored before the rows were keyed by id and version
+    classifier_base64: String, // Serialized model as base64
+    #[serde(default)]
+    model_kind: ModelKind,
+    #[serde(default)]
+    schema_hash: Option<u64>,
+    #[serde(default)]
+    model_version: Option<u64>,
+}
+
+// Version of a classifier, retrieved without its serialized model to check for a new one
+#[derive(serde::Serialize, serde::Deserialize, Default, Debug)]
+struct ClassifierVersion {
+    #[serde(default)]
+    model_id: Option<String>,
+    #[serde(default)]
+    model_version: Option<u64>,
+}
+
+// Which of the models stored in the database to use, selected with "model_id" and "model_version" in "init-payload"
+pub struct ModelSelector {
+    pub model_id: String,               // Id given to classifier_training, "base64_model" unless trained with another one
+    pub model_version: Option<u64>,     // Pinned version, None for the latest version of the id (swapped on reload)
+}
+
+// Sent to "model_error" when the selected model cannot be retrieved or swapped in
+#[derive(serde::Serialize)]
+struct ModelError<'a> {
+    model_id: &'a str,
+    model_version: Option<u64>,
+    error: &'a str,
+}
+
+impl ModelSelector {
+    pub fn from_arguments(arguments: &HashMap<&str, &str>) -> ModelSelector {
+        ModelSelector {
+            model_id: arguments.get("model_id").unwrap_or(&"base64_model").to_string(),
+            model_version: arguments.get("model_version").map(|value| value.parse::<u64>().expect("Invalid model version provided")),
+        }
+    }
+
+    // classifier_training keys its rows "<model_id>@<version>", the model id being in the metadata.
+    // Rows keyed by the bare id, written before, are still selected, behind any versioned row
+    fn query(&self) -> String {
+        self.select("metadata")
+    }
+
+    // Same row as query(), with only the id and the version of the model in its metadata
+    fn version_query(&self) -> String {
+        self.select("json_object('model_id', json_extract(metadata, '$.model_id'), 'model_version', json_extract(metadata, '$.model_version')) AS metadata")
+    }
+
+    fn select(&self, metadata: &str) -> String {
+        // Quotes doubled, the id being spliced into the statement
+        let model_id = self.model_id.replace('\'', "''");
+        let mut query = format!(
+            "SELECT id, {} FROM WorkflowState WHERE (id = '{}' OR json_extract(metadata, '$.model_id') = '{}')",
+            metadata, model_id, model_id
+        );
+        if let Some(model_version) = self.model_version {
+            query.push_str(&format!(" AND json_extract(metadata, '$.model_version') = {}", model_version));
+        }
+        query.push_str(" ORDER BY json_extract(metadata, '$.model_version') DESC LIMIT 1");
+        query
+    }
+
+    fn describe(&self) -> String {
+        match self.model_version {
+            Some(model_version) => format!("model {} version {}", self.model_id, model_version),
+            None => format!("model {}", self.model_id),
+        }
+    }
+
+    // Casts an error about the selected model to "model_error"
+    pub fn report_error(&self, error: &str) {
+        log::info!("{}", error);
+        let record = ModelError {
+            model_id: &self.model_id,
+            model_version: self.model_version,
+            error,
+        };
+        match serde_json::to_string(&record) {
+            Ok(json) => cast("model_error", json.as_bytes()),
+            Err(e) => log::info!("Error serializing model error: {}", e),
+        }
+    }
+
+    // Retrieves the row of the selected model, an error if the database could not be reached or has no such row
+    fn fetch(&self) -> Result<ClassifierData, String> {
+        let result: SqlxRow<ClassifierData> = call_wrapper(&self.query()).ok_or_else(|| format!("The database could not be reached to retrieve {}", self.describe()))?;
+        if result.id != self.model_id && result.metadata.model_id.as_deref() != Some(self.model_id.as_str()) {
+            return Err(format!("No {} in the database", self.describe()));
+        }
+        Ok(result.metadata)
+    }
+
+    // Retrieves the version of the selected model only, without its serialized model
+    fn fetch_version(&self) -> Result<Option<u64>, String> {
+        let result: SqlxRow<ClassifierVersion> = call_wrapper(&self.version_query()).ok_or_else(|| format!("The database could not be reached to check {}", self.describe()))?;
+        if result.id != self.model_id && result.metadata.model_id.as_deref() != Some(self.model_id.as_str()) {
+            return Err(format!("No {} in the database", self.describe()));
+        }
+        Ok(result.metadata.model_version)
+    }
+}
+
+fn call_wrapper<M: serde::de::DeserializeOwned + Default>(msg: &str) -> Option<SqlxRow<M>> {
+    match call("sqlx_database", msg.as_bytes()) {
+        CallRet::Reply(msg) => {
+            let reply = std::str::from_utf8(&msg).unwrap_or("not UTF-8");
+            // log::info!("Response from DB: {:?}", reply);
+            let cur_state: SqlxRow<M> = serde_json::from_str(reply).unwrap_or_default();
+            Some(cur_state)
+        }
+        CallRet::NoReply => {
+            log::warn!("Received empty reply from the DB");
+            None
+        }
+        CallRet::Err => {
+            log::error!("Error while calling the DB");
+            None
+        }
+    }
+}
+
+// Deserializes the classifier stored in a WorkflowState row
+fn decode(data: ClassifierData) -> Result<Model, String> {
+    let serialized_model = base64::engine::general_purpose::STANDARD.decode(&data.classifier_base64).map_err(|e| format!("Invalid model encoding: {}", e))?;
+
+    let classifier = activity_schema::models::deserialize(data.model_kind, &serialized_model)
+        .map_err(|e| format!("Invalid serialized {} model: {}", data.model_kind.name(), e))?;
+
+    Ok(Model {
+        classifier,
+        schema_hash: data.schema_hash,
+        version: data.model_version,
+    })
+}
+
+impl Model {
+    // Retrieves the selected model from the database
+    pub fn load(selector: &ModelSelector) -> Result<Model, String> {
+        let metadata = selector.fetch()?;
+        decode(metadata).map_err(|e| format!("{}: {}", selector.describe(), e))
+    }
+
+    // Retrieves the selected model if its version differs from this one's, None if it does not. Only the
+    // version is queried first, the serialized model being retrieved, at that version, once it differs.
+    // The new model must take the features this one takes, those of the running extract_features
+    pub fn reload(&self, selector: &ModelSelector) -> Result<Option<Model>, String> {
+        let model_version = selector.fetch_version()?;
+        if model_version == self.version {
+            return Ok(None);
+        }
+
+        let selector = ModelSelector {
+            model_id: selector.model_id.clone(),
+            model_version: model_version.or(selector.model_version),
+        };
+        let metadata = selector.fetch()?;
+
+        let model = decode(metadata).map_err(|e| format!("{}: {}", selector.describe(), e))?;
+        if let (Some(schema_hash), Some(new_schema_hash)) = (self.schema_hash, model.schema_hash) {
+            if new_schema_hash != schema_hash {
+                return Err(format!(
+                    "model version {:?} expects features schema {:016x} instead of {:016x}",
+                    model.version, new_schema_hash, schema_hash
+                ));
+            }
+        }
+
+        Ok(Some(model))
+    }
+
+    pub fn kind(&self) -> ModelKind {
+        self.classifier.kind()
+    }
+
+    // Whether features of this schema can be fed to the model
+    pub fn accepts(&self, schema_hash: u64) -> Result<(), String> {
+        match self.schema_hash {
+            Some(model_schema_hash) if model_schema_hash != schema_hash => Err(format!(
+                "features schema {:016x} does not match the model's {:016x}",
+                schema_hash, model_schema_hash
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    // Features in the column order used by classifier_training. The prediction is rejected, i.e. Unknown,
+    // if the predicted class got less than `confidence_threshold` of the votes
+    pub fn predict(&self, features_vec: Vec<f64>, confidence_threshold: f64) -> Result<Prediction, String> {
+        let sample = DenseMatrix::from_2d_vec(&vec![features_vec]);
+
+        // The label and the votes come from a single run of the model
+        let (label, class_votes) = self.classifier.predict_votes(&sample).map_err(|e| e.to_string())?[0];
+
+        log::info!("Classified the received features: {:?}, votes: {:?}", label, class_votes);
+
+        let classification = u32::try_from(label)
+            .ok()
+            .and_then(Classification::from_label)
+            .ok_or_else(|| format!("Classifier returned an unknown label: {}", label))?;
+
+        let confidence = class_votes[classification.label() as usize];
+        if confidence < confidence_threshold {
+            log::info!("Rejected {:?}, confidence {:.2} is below {:.2}", classification, confidence, confidence_threshold);
+            return Ok(Prediction { classification: Classification::Unknown, class_votes });
+        }
+
+        Ok(Prediction { classification, class_votes })
+    }
+}
+
+// Retrieval of the model while none is available: retries with exponential backoff, and the feature
+// payloads received in the meantime, classified once the model is installed. Used by classify and by
+// the fused function, which buffers the features of its windows. The payloads are handed over to
+// EDGELESS state sync as the "model_backlog" section (see synced), so that a cold start after a
+// migration classifies those buffered before it. State sync takes the whole backlog at every buffered
+// payload: it is bounded in payloads and in bytes, and synced in bincode, which keeps the payloads as raw bytes
+
+pub struct Loading {
+    retry_ms: u64,                          // Delay before the first retry, doubled after each failed one
+    retry_max_ms: u64,
+    next_retry_ms: u64,
+    buffer_size: usize,                     // Payloads kept while no model is available, the oldest dropped beyond it
+    buffer_bytes: usize,                    // Bytes of the payloads kept, the oldest dropped beyond it
+    backlog: Backlog,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Backlog {
+    messages: VecDeque<BufferedMessage>,
+    dropped_batches: u64,                   // Dropped since the function started, the buffer being full
+}
+
+impl Backlog {
+    fn bytes(&self) -> usize {
+        self.messages.iter().map(|buffered| buffered.message.len()).sum()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BufferedMessage {
+    pub received_us: Option<u64>,           // Arrival of the payload, so that the buffering counts in the compute time of the classification
+    pub message: Vec<u8>,                   // Payload as received, in its encoding
+}
+
+// Sent to "model_status" whenever the model becomes available or a retrieval fails
+#[derive(Serialize)]
+struct ModelStatus<'a> {
+    model_id: &'a str,
+    model_version: Option<u64>,             // Version of the model in use, or the pinned one while none is available
+    available: bool,
+    buffered_batches: usize,
+    dropped_batches: u64,
+    retry_in_ms: Option<u64>,               // Delay before the next retrieval, None once the model is available
+}
+
+impl Loading {
+    // Restores the payloads buffered before a migration, if any
+    pub fn new(arguments: &HashMap<&str, &str>, init_state: Option<&[u8]>) -> Loading {
+        let retry_ms = arguments.get("model_retry_ms").unwrap_or(&"1000").parse::<u64>().expect("Invalid model retry delay provided");
+        let retry_max_ms = arguments.get("model_retry_max_ms").unwrap_or(&"60000").parse::<u64>().expect("Invalid model retry maximum delay provided");
+        let buffer_size = arguments.get("model_buffer_size").unwrap_or(&"100").parse::<usize>().expect("Invalid model buffer size provided");
+        let buffer_bytes = arguments.get("model_buffer_bytes").unwrap_or(&"262144").parse::<usize>().expect("Invalid model buffer bytes provided");
+        assert!(retry_ms > 0 && retry_ms <= retry_max_ms, "The model retry delay must be between 1 and the maximum delay");
+
+        let backlog = match synced::restore(init_state, "model_backlog").map(|backlog| bincode::deserialize::<Backlog>(&backlog)) {
+            Some(Ok(backlog)) => {
+                log::info!("Restored {} buffered batches", backlog.messages.len());
+                backlog
+            }
+            Some(Err(e)) => {
+                log::info!("Error deserializing the buffered batches, starting without them: {}", e);
+                Backlog::default()
+            }
+            None => Backlog::default(),
+        };
+
+        Loading {
+            retry_ms,
+            retry_max_ms,
+            next_retry_ms: retry_ms,
+            buffer_size,
+            buffer_bytes,
+            backlog,
+        }
+    }
+
+    // Delay before the next retrieval, doubling the following one
+    pub fn next_retry(&mut self) -> u64 {
+        let retry_in_ms = self.next_retry_ms;
+        self.next_retry_ms = (retry_in_ms.saturating_mul(2)).min(self.retry_max_ms);
+        retry_in_ms
+    }
+
+    // Keeps a payload until a model is available, dropping the oldest ones while the buffer is full
+    pub fn buffer(&mut self, received_us: Option<u64>, message: &[u8]) {
+        self.backlog.messages.push_back(BufferedMessage {
+            received_us,
+            message: message.to_vec(),
+        });
+        while self.backlog.messages.len() > self.buffer_size || self.backlog.bytes() > self.buffer_bytes {
+            self.backlog.messages.pop_front();
+            self.backlog.dropped_batches += 1;
+            log::info!("Model buffer full, dropped a batch ({} dropped so far)", self.backlog.dropped_batches);
+        }
+        log::info!("No classifier available, {} batches buffered", self.backlog.messages.len());
+        self.persist();
+    }
+
+    // Payloads buffered so far, to be classified by the model just installed
+    pub fn take_buffered(&mut self) -> Vec<BufferedMessage> {
+        self.next_retry_ms = self.retry_ms;
+        let messages = self.backlog.messages.drain(..).collect();
+        self.persist();
+        messages
+    }
+
+    // Casts the availability of the model to "model_status"
+    pub fn report_status(&self, selector: &ModelSelector, model_version: Option<u64>, retry_in_ms: Option<u64>) {
+        let record = ModelStatus {
+            model_id: &selector.model_id,
+            model_version,
+            available: retry_in_ms.is_none(),
+            buffered_batches: self.backlog.messages.len(),
+            dropped_batches: self.backlog.dropped_batches,
+            retry_in_ms,
+        };
+        match serde_json::to_string(&record) {
+            Ok(json) => cast("model_status", json.as_bytes()),
+            Err(e) => log::info!("Error serializing model status: {}", e),
+        }
+    }
+
+    // Hands the buffered payloads over to EDGELESS state sync
+    fn persist(&self) {
+        match bincode::serialize(&self.backlog) {
+            Ok(serialized_backlog) => synced::persist("model_backlog", serialized_backlog),
+            Err(e) => log::info!("Error serializing buffered batches: {}", e),
+        }
+    }
+}
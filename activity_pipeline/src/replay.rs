@@ -0,0 +1,128 @@
+use edgeless_function::*;
+use serde::{Serialize, Deserialize};
+use std::collections::VecDeque;
+
+use crate::{AccelerometerData, Classification};
+
+// A recorded sample, same layout as the rows read by classifier_training
+#[derive(Debug, Deserialize)]
+struct TraceRow {
+    activity: String,
+    accel_x: f64,
+    accel_y: f64,
+    accel_z: f64,
+}
+
+// A trace is stored as consecutive WorkflowState rows with id "<trace_id>_<chunk_index>",
+// each holding a slice of the recording in its metadata
+#[derive(Debug, Deserialize, Default)]
+struct TraceChunk {
+    rows: Vec<TraceRow>,
+}
+
+#[derive(Deserialize, Default)]
+struct SqlxTraceChunk {
+    id: String,
+    metadata: TraceChunk,
+}
+
+// Outcome of fetching a chunk of the trace
+enum ChunkLoad {
+    Loaded,
+    Missing,                    // The database holds no such chunk: the trace is over
+    Failed(String),             // The database could not be reached or did not reply with a chunk
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TraceReplay {
+    trace_id: String,
+    loop_trace: bool,           // Restart from the first chunk once the trace is over
+    next_chunk: u64,
+    buffer: VecDeque<(Classification, AccelerometerData)>,
+}
+
+impl TraceReplay {
+    pub fn new(trace_id: &str, loop_trace: bool) -> TraceReplay {
+        TraceReplay {
+            trace_id: trace_id.to_string(),
+            loop_trace,
+            next_chunk: 0,
+            buffer: VecDeque::new(),
+        }
+    }
+
+    // Returns the next num_samples samples of the trace with their activity, fewer at the end
+    // of a non-looping trace; None once the trace is over. A chunk that could not be fetched is an error,
+    // the samples buffered so far being kept for the next attempt
+    pub fn next_samples(&mut self, num_samples: usize) -> Result<Option<Vec<(Classification, AccelerometerData)>>, String> {
+        let mut wrapped = false;
+
+        while self.buffer.len() < num_samples {
+            match self.load_chunk(self.next_chunk) {
+                ChunkLoad::Loaded => self.next_chunk += 1,
+                ChunkLoad::Missing if self.loop_trace && self.next_chunk > 0 && !wrapped => {
+                    log::info!("Trace '{}' over, replaying it from the start", self.trace_id);
+                    self.next_chunk = 0;
+                    wrapped = true;
+                }
+                ChunkLoad::Missing => break,
+                ChunkLoad::Failed(e) => return Err(format!("chunk #{} of trace '{}' not fetched: {}", self.next_chunk, self.trace_id, e)),
+            }
+        }
+
+        if self.buffer.is_empty() {
+            return Ok(None);
+        }
+
+        let num_samples = num_samples.min(self.buffer.len());
+        Ok(Some(self.buffer.drain(..num_samples).collect()))
+    }
+
+    // Fetches a chunk of the trace through the sqlx resource and appends its samples to the buffer
+    fn load_chunk(&mut self, chunk_index: u64) -> ChunkLoad {
+        let chunk_id = format!("{}_{}", self.trace_id, chunk_index);
+        // Quotes doubled, the id being spliced into the statement, as in ModelSelector
+        let query = format!("SELECT id, metadata FROM WorkflowState WHERE id = '{}'", chunk_id.replace('\'', "''"));
+
+        let reply = match call("sqlx_database", query.as_bytes()) {
+            CallRet::Reply(msg) => msg,
+            CallRet::NoReply => return ChunkLoad::Failed("empty reply from the DB".to_string()),
+            CallRet::Err => return ChunkLoad::Failed("error while calling the DB".to_string()),
+        };
+        let reply = match std::str::from_utf8(&reply) {
+            Ok(reply) => reply.trim(),
+            Err(_) => return ChunkLoad::Failed("reply not in UTF-8".to_string()),
+        };
+
+        // No row: an empty result, or a row that is not the requested chunk
+        if reply.is_empty() || reply == "null" || reply == "[]" || reply == "{}" {
+            return ChunkLoad::Missing;
+        }
+        let chunk = match serde_json::from_str::<SqlxTraceChunk>(reply) {
+            Ok(chunk) if chunk.id == chunk_id => chunk.metadata,
+            Ok(_) => return ChunkLoad::Missing,
+            Err(e) => return ChunkLoad::Failed(format!("invalid chunk: {}", e)),
+        };
+
+        if chunk.rows.is_empty() {
+            return ChunkLoad::Missing;
+        }
+
+        for row in chunk.rows {
+            match Classification::from_activity(&row.activity) {
+                Some(class) => self.buffer.push_back((
+                    class,
+                    AccelerometerData {
+                        x: row.accel_x,
+                        y: row.accel_y,
+                        z: row.accel_z,
+                    },
+                )),
+                None => log::warn!("Skipping sample with unknown activity '{}'", row.activity),
+            }
+        }
+
+        log::info!("Loaded chunk #{} of trace '{}'", chunk_index, self.trace_id);
+        ChunkLoad::Loaded
+    }
+}
@@ -0,0 +1,199 @@
+use edgeless_function::*;
+use serde::Serialize;
+
+use crate::{Classification, HopTimestamps, WindowId, NUM_CLASSES};
+
+// Online scoring of the classifications against the ground truth and per-hop latency breakdown of
+// each batch. Shared by handle_class_result and by the fused function (functions/fused_activity_recognition),
+// so that both write the same records to accuracy_log and latency_breakdown
+
+#[derive(Default)]
+pub struct Scoreboard {
+    overall: Scores,
+    devices: std::collections::HashMap<u32, Scores>,           // Scores of each emulated device
+}
+
+#[derive(Default)]
+struct Scores {
+    scored_batches: u64,                                        // Accepted classifications, i.e. not Unknown
+    correct_batches: u64,
+    rejected_batches: u64,                                      // Classifications rejected by classify as not confident enough
+    confusion_matrix: [[u64; NUM_CLASSES]; NUM_CLASSES],       // Rows: true class, columns: predicted class
+}
+
+impl Scores {
+    fn add(&mut self, true_class: Classification, predicted_class: Classification) {
+        if predicted_class == Classification::Unknown {
+            self.rejected_batches += 1;
+            return;
+        }
+        self.scored_batches += 1;
+        if true_class == predicted_class {
+            self.correct_batches += 1;
+        }
+        self.confusion_matrix[true_class as usize][predicted_class as usize] += 1;
+    }
+
+    // NaN, written as null to accuracy_log, until a classification has been accepted
+    fn accuracy(&self) -> f64 {
+        self.correct_batches as f64 / self.scored_batches as f64
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct AccuracyRecord {
+    batch_id: u64,
+    window_index: u32,                      // Among the windows completed by the batch
+    device_id: u32,
+    true_class: Classification,
+    predicted_class: Classification,
+    model_version: Option<u64>,             // Version of the model that produced the prediction
+    confidence: Option<f64>,                // Fraction of the votes the predicted class got, None if classify sent no votes
+    correct: bool,
+    running_accuracy: f64,                  // Over the accepted classifications
+    device_running_accuracy: f64,
+    rejected_batches: u64,
+    device_rejected_batches: u64,
+    confusion_matrix: [[u64; NUM_CLASSES]; NUM_CLASSES],
+}
+
+// Time spent in each stage and on the link that brought the payload to it
+#[derive(Debug, Serialize)]
+struct HopLatency {
+    stage: String,
+    network_us: Option<i64>,                // Previous stage's send to this stage's receive. Signed, nodes' clocks may be skewed
+    compute_us: Option<i64>,                // This stage's receive to its send
+}
+
+#[derive(Debug, Serialize)]
+struct LatencyBreakdown {
+    batch_id: u64,
+    window_index: u32,
+    device_id: u32,
+    encoding: Option<String>,               // Encoding of the payloads along the workflow, None if the stages exchange none
+    hops: Vec<HopLatency>,
+    end_to_end_us: Option<i64>,             // First stage's receive to the end of the last stage
+}
+
+pub fn display_class_result(result: &Classification) -> String {
+    match result {
+        Classification::Jogging => "'JOGGING' activity detected".to_string(),
+        Classification::Walking => "'WALKING' activity detected".to_string(),
+        Classification::Standing => "'STANDING' activity detected".to_string(),
+        Classification::Stairs => "'STAIRS' activity detected".to_string(),
+        Classification::Sitting => "'SITTING' activity detected".to_string(),
+        Classification::Unknown => "Uncertain activity, classification rejected".to_string(),
+    }
+}
+
+impl Scoreboard {
+    pub fn new() -> Scoreboard {
+        Scoreboard::default()
+    }
+
+    // Scores a classification and casts the updated accuracy to "accuracy_log". Rejected classifications
+    // (Unknown) are counted apart and left out of the accuracy and of the confusion matrix
+    pub fn score(
+        &mut self,
+        window_id: WindowId,
+        device_id: u32,
+        true_class: Classification,
+        predicted_class: Classification,
+        model_version: Option<u64>,
+        class_votes: Option<[f64; NUM_CLASSES]>,
+    ) {
+        // The ground truth is always one of the activities, a payload claiming otherwise cannot be scored
+        if true_class == Classification::Unknown {
+            log::info!("Window #{} (device #{}): unknown true class, not scored", window_id, device_id);
+            return;
+        }
+
+        let correct = true_class == predicted_class;
+        let confidence = class_votes.map(|votes| votes.into_iter().fold(0.0, f64::max));
+        self.overall.add(true_class, predicted_class);
+
+        let device_scores = self.devices.entry(device_id).or_default();
+        device_scores.add(true_class, predicted_class);
+
+        let running_accuracy = self.overall.accuracy();
+        let device_running_accuracy = device_scores.accuracy();
+        if predicted_class == Classification::Unknown {
+            log::info!(
+                "Window #{} (device #{}): true class {:?}, classification rejected with confidence {:?}, {} rejected batches, {} of the device",
+                window_id,
+                device_id,
+                true_class,
+                confidence,
+                self.overall.rejected_batches,
+                device_scores.rejected_batches
+            );
+        }
+        log::info!(
+            "Window #{} (device #{}): true class {:?}, running accuracy {:.4} ({}/{}), device running accuracy {:.4} ({}/{})",
+            window_id,
+            device_id,
+            true_class,
+            running_accuracy,
+            self.overall.correct_batches,
+            self.overall.scored_batches,
+            device_running_accuracy,
+            device_scores.correct_batches,
+            device_scores.scored_batches
+        );
+
+        let record = AccuracyRecord {
+            batch_id: window_id.batch_id,
+            window_index: window_id.window_index,
+            device_id,
+            true_class,
+            predicted_class,
+            model_version,
+            confidence,
+            correct,
+            running_accuracy,
+            device_running_accuracy,
+            rejected_batches: self.overall.rejected_batches,
+            device_rejected_batches: device_scores.rejected_batches,
+            confusion_matrix: self.overall.confusion_matrix,
+        };
+
+        match serde_json::to_string(&record) {
+            Ok(json) => cast("accuracy_log", json.as_bytes()),
+            Err(e) => log::info!("Error serializing accuracy record: {}", e),
+        }
+    }
+}
+
+// Casts the per-hop latency breakdown of a window to "latency_breakdown"
+pub fn report_latency(window_id: WindowId, device_id: u32, encoding: Option<String>, hops: &[HopTimestamps]) {
+    let breakdown = latency_breakdown(window_id, device_id, encoding, hops);
+    match serde_json::to_string(&breakdown) {
+        Ok(json) => cast("latency_breakdown", json.as_bytes()),
+        Err(e) => log::info!("Error serializing latency breakdown: {}", e),
+    }
+}
+
+fn latency_breakdown(window_id: WindowId, device_id: u32, encoding: Option<String>, hops: &[HopTimestamps]) -> LatencyBreakdown {
+    let elapsed = |from: Option<u64>, to: Option<u64>| -> Option<i64> {
+        Some(to? as i64 - from? as i64)
+    };
+
+    let hop_latencies = hops
+        .iter()
+        .enumerate()
+        .map(|(i, hop)| HopLatency {
+            stage: hop.stage.clone(),
+            network_us: if i == 0 { None } else { elapsed(hops[i - 1].sent_us, hop.received_us) },
+            compute_us: elapsed(hop.received_us, hop.sent_us),
+        })
+        .collect();
+
+    LatencyBreakdown {
+        batch_id: window_id.batch_id,
+        window_index: window_id.window_index,
+        device_id,
+        encoding,
+        hops: hop_latencies,
+        end_to_end_us: elapsed(hops.first().and_then(|hop| hop.received_us), hops.last().and_then(|hop| hop.sent_us)),
+    }
+}
@@ -0,0 +1,58 @@
+use serde::{Serialize, Deserialize};
+
+// The LCG of edgeless_function (edgeless_function::lcg::Lcg), whose whole state is the last value it
+// produced: that word is persisted as is, so a restored or cloned generator continues the sequence of
+// Lcg at no cost, which Lcg itself, keeping its state private, does not allow
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Rng {
+    state: u32,
+}
+
+impl Rng {
+    pub fn new(seed: u32) -> Rng {
+        Rng { state: seed }
+    }
+
+    // Uniform value in [0, 1], the 31-bit state over 2^31 as Lcg computes it (the f32 may round up to 1)
+    pub fn rand(&mut self) -> f32 {
+        self.state = self.state.wrapping_mul(1103515245).wrapping_add(12345) % (1u32 << 31);
+        self.state as f32 / (1u32 << 31) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use edgeless_function::lcg::Lcg;
+
+    #[test]
+    fn draws_are_those_of_lcg() {
+        for seed in [0, 1, 12345, 7919 * 3, u32::MAX] {
+            let mut rng = Rng::new(seed);
+            let mut lcg = Lcg::new(seed);
+            for draw in 0..10000 {
+                assert_eq!(rng.rand().to_bits(), lcg.rand().to_bits(), "Draw {} of seed {} differs from Lcg", draw, seed);
+            }
+        }
+    }
+
+    #[test]
+    fn restored_rng_continues_the_sequence() {
+        let mut rng = Rng::new(12345);
+        let mut lcg = Lcg::new(12345);
+        for _ in 0..1000 {
+            rng.rand();
+            lcg.rand();
+        }
+
+        let mut restored: Rng = serde_json::from_slice(&serde_json::to_vec(&rng).unwrap()).unwrap();
+        let mut cloned = rng.clone();
+        for _ in 0..1000 {
+            let value = lcg.rand();
+            assert!((0.0..=1.0).contains(&value));
+            assert_eq!(rng.rand(), value);
+            assert_eq!(restored.rand(), value);
+            assert_eq!(cloned.rand(), value);
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use activity_schema::NUM_CLASSES;
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+
+use crate::rng::Rng;
+use crate::Classification;
+
+// How the activity of each segment is chosen, selected with "scenario" or "transition_matrix" in "init-payload".
+// Since "init-payload" is split on commas, their entries are separated by semicolons
+#[derive(Clone, Serialize, Deserialize)]
+pub enum ClassSequence {
+    Uniform,                                    // A uniformly random activity per segment
+    Scenario {
+        steps: Vec<(Classification, u64)>,      // Activity and its duration in samples, replayed in a loop
+        position: u64,                          // Samples elapsed since the start of the current loop
+    },
+    Markov {
+        transitions: [[f64; NUM_CLASSES]; NUM_CLASSES],        // Row: current activity, column: next activity
+        current: Option<Classification>,
+    },
+}
+
+impl ClassSequence {
+    pub fn from_arguments(arguments: &HashMap<&str, &str>, sample_rate_hz: f64) -> ClassSequence {
+        if let Some(scenario) = arguments.get("scenario") {
+            // e.g. scenario=sitting:120s;walking:60s;stairs:30s
+            let steps: Vec<(Classification, u64)> = scenario
+                .split(';')
+                .map(|step| {
+                    let (activity, duration) = step.split_once(':').unwrap_or_else(|| panic!("Invalid scenario step provided: {}", step));
+                    let class = Classification::from_activity(activity.trim()).unwrap_or_else(|| panic!("Invalid activity provided: {}", activity));
+                    let seconds = duration.trim().trim_end_matches('s').parse::<f64>().unwrap_or_else(|_| panic!("Invalid duration provided: {}", duration));
+                    (class, (seconds * sample_rate_hz).round() as u64)
+                })
+                .collect();
+            assert!(steps.iter().any(|(_, samples)| *samples > 0), "The scenario must last at least one sample");
+
+            ClassSequence::Scenario { steps, position: 0 }
+        } else if let Some(matrix) = arguments.get("transition_matrix") {
+            // e.g. transition_matrix=0.9:0.1:0:0:0;... one row per activity, in label order
+            let mut transitions = [[0.0; NUM_CLASSES]; NUM_CLASSES];
+            let rows: Vec<&str> = matrix.split(';').collect();
+            assert!(rows.len() == NUM_CLASSES, "The transition matrix must have {} rows", NUM_CLASSES);
+
+            for (i, row) in rows.iter().enumerate() {
+                let values: Vec<f64> = row
+                    .split(':')
+                    .map(|value| value.trim().parse::<f64>().unwrap_or_else(|_| panic!("Invalid transition probability provided: {}", value)))
+                    .collect();
+                assert!(values.len() == NUM_CLASSES, "Each row of the transition matrix must have {} values", NUM_CLASSES);
+
+                let total: f64 = values.iter().sum();
+                assert!(total > 0.0, "Each row of the transition matrix must have a positive sum");
+                for (j, value) in values.iter().enumerate() {
+                    transitions[i][j] = value / total;          // Rows are normalized
+                }
+            }
+
+            ClassSequence::Markov { transitions, current: None }
+        } else {
+            ClassSequence::Uniform
+        }
+    }
+
+    // Activity of the next segment and its length, at most max_len samples. A scenario segment ends at the
+    // end of its step, so that every sample gets the activity of its own position in the scenario
+    pub fn next_class(&mut self, rng: &mut Rng, max_len: usize) -> (Classification, usize) {
+        match self {
+            ClassSequence::Uniform => (uniform_class(rng), max_len),
+            ClassSequence::Scenario { steps, position } => {
+                let total: u64 = steps.iter().map(|(_, samples)| samples).sum();
+                let mut offset = *position % total;
+
+                for (class, samples) in steps.iter() {
+                    if offset < *samples {
+                        let segment_len = (*samples - offset).min(max_len as u64);
+                        *position = (*position + segment_len) % total;
+                        return (*class, segment_len as usize);
+                    }
+                    offset -= samples;
+                }
+                unreachable!("The offset is always within the scenario");
+            }
+            ClassSequence::Markov { transitions, current } => {
+                let next = match current {
+                    None => uniform_class(rng),
+                    Some(class) => {
+                        let row = &transitions[*class as usize];
+                        let draw = rng.rand() as f64;
+                        let mut cumulative = 0.0;
+                        let mut next_label = NUM_CLASSES - 1;
+                        for (label, probability) in row.iter().enumerate() {
+                            cumulative += probability;
+                            if draw < cumulative {
+                                next_label = label;
+                                break;
+                            }
+                        }
+                        Classification::from_label(next_label as u32).unwrap()
+                    }
+                };
+                *current = Some(next);
+                (next, max_len)
+            }
+        }
+    }
+}
+
+fn uniform_class(rng: &mut Rng) -> Classification {
+    let chosen_label = ((rng.rand() * NUM_CLASSES as f32) as u32).min(NUM_CLASSES as u32 - 1);
+    Classification::from_label(chosen_label).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scenario(value: &str, sample_rate_hz: f64) -> ClassSequence {
+        ClassSequence::from_arguments(&HashMap::from([("scenario", value)]), sample_rate_hz)
+    }
+
+    // Activities of the samples of consecutive segments of at most max_len samples
+    fn labels(sequence: &mut ClassSequence, max_len: usize, count: usize) -> Vec<Classification> {
+        let mut rng = Rng::new(0);
+        let mut labels = Vec::new();
+        while labels.len() < count {
+            let (class, segment_len) = sequence.next_class(&mut rng, max_len);
+            assert!(segment_len > 0 && segment_len <= max_len);
+            labels.extend(vec![class; segment_len]);
+        }
+        labels.truncate(count);
+        labels
+    }
+
+    #[test]
+    fn scenario_durations_are_converted_to_samples() {
+        match scenario("sitting:120s; walking:1.5s;stairs:0", 20.0) {
+            ClassSequence::Scenario { steps, position } => {
+                assert_eq!(steps, vec![(Classification::Sitting, 2400), (Classification::Walking, 30), (Classification::Stairs, 0)]);
+                assert_eq!(position, 0);
+            }
+            _ => panic!("Not parsed as a scenario"),
+        }
+    }
+
+    #[test]
+    fn scenario_steps_last_their_duration_in_samples() {
+        // Steps of 7 and 3 samples, drawn by segments of 4: every sample keeps the activity of its position
+        let mut sequence = scenario("walking:7s;sitting:3s", 1.0);
+        let expected: Vec<Classification> = [Classification::Walking; 7]
+            .into_iter()
+            .chain([Classification::Sitting; 3])
+            .cycle()
+            .take(45)
+            .collect();
+        assert_eq!(labels(&mut sequence, 4, 45), expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid activity provided")]
+    fn scenario_rejects_unknown_activities() {
+        scenario("sitting:10s;flying:10s", 20.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one sample")]
+    fn scenario_rejects_empty_scenarios() {
+        scenario("sitting:0s", 20.0);
+    }
+}
@@ -0,0 +1,44 @@
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+use edgeless_function::*;
+
+// EDGELESS state sync keeps a single state per function, while a function may sync several parts of
+// its state (the generator, the backlog of the model retrieval), each at its own pace: each part is a
+// section of that state, replaced on its own, the state synced being all of the sections in bincode
+
+static SECTIONS: OnceLock<Mutex<BTreeMap<String, Vec<u8>>>> = OnceLock::new();
+
+fn sections() -> &'static Mutex<BTreeMap<String, Vec<u8>>> {
+    SECTIONS.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+// Section of the state synced by a previous instance, None if the function has not been relocated or
+// the section has not been synced. The other sections are kept until restored or replaced, so that
+// syncing a section before they are restored does not lose them
+pub fn restore(init_state: Option<&[u8]>, section: &str) -> Option<Vec<u8>> {
+    let synced: BTreeMap<String, Vec<u8>> = match init_state.map(bincode::deserialize) {
+        Some(Ok(synced)) => synced,
+        Some(Err(e)) => {
+            log::info!("Error deserializing the synced state, starting without its {}: {}", section, e);
+            return None;
+        }
+        None => return None,
+    };
+
+    let restored = synced.get(section).cloned();
+    let mut sections = sections().lock().unwrap();
+    for (name, bytes) in synced {
+        sections.entry(name).or_insert(bytes);
+    }
+    restored
+}
+
+// Replaces a section and hands the whole state over to EDGELESS state sync
+pub fn persist(section: &str, bytes: Vec<u8>) {
+    let mut sections = sections().lock().unwrap();
+    sections.insert(section.to_string(), bytes);
+    match bincode::serialize(&*sections) {
+        Ok(serialized_state) => sync(&serialized_state),
+        Err(e) => log::info!("Error serializing the synced state: {}", e),
+    }
+}
@@ -0,0 +1,153 @@
+use serde::{Serialize, Deserialize};
+
+use crate::rng::Rng;
+use crate::{AccelerometerData, Classification};
+
+const SATURATION: f64 = 19.6;           // Clip of the accelerometer in the recorded tables (±2g)
+
+// Signal model of an activity, fitted on the hard-coded sample tables
+struct ActivityProfile {
+    gait_frequency_hz: f64,
+    gravity: [f64; 3],              // Mean value of each axis, i.e. gravity as seen by the device
+    amplitude: [f64; 3],            // Peak amplitude of the periodic component of each axis
+    noise_std_dev: f64,
+}
+
+fn activity_profile(class: Classification) -> ActivityProfile {
+    match class {
+        Classification::Jogging => ActivityProfile {
+            gait_frequency_hz: 2.6,
+            gravity: [0.23, -7.23, 0.67],
+            amplitude: [6.5, 9.5, 8.5],
+            noise_std_dev: 1.5,
+        },
+        Classification::Walking => ActivityProfile {
+            gait_frequency_hz: 1.8,
+            gravity: [11.35, -1.08, 0.67],
+            amplitude: [5.8, 2.2, 4.4],
+            noise_std_dev: 0.8,
+        },
+        Classification::Standing => ActivityProfile {
+            gait_frequency_hz: 0.2,
+            gravity: [9.22, -0.7, 1.45],
+            amplitude: [0.3, 1.8, 1.8],
+            noise_std_dev: 0.2,
+        },
+        Classification::Stairs => ActivityProfile {
+            gait_frequency_hz: 1.5,
+            gravity: [13.66, -1.31, -1.74],
+            amplitude: [4.5, 5.2, 3.5],
+            noise_std_dev: 1.0,
+        },
+        Classification::Sitting => ActivityProfile {
+            gait_frequency_hz: 0.2,
+            gravity: [-2.72, -4.22, 8.43],
+            amplitude: [0.7, 1.4, 0.6],
+            noise_std_dev: 0.2,
+        },
+        Classification::Unknown => unreachable!("Samples are generated for the activities of Classification::ALL"),
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SyntheticGenerator {
+    sample_rate_hz: f64,
+}
+
+impl SyntheticGenerator {
+    pub fn new(sample_rate_hz: f64) -> SyntheticGenerator {
+        SyntheticGenerator { sample_rate_hz }
+    }
+
+    // Generates batch_size samples of the given activity. Gait frequency, amplitude, phase and
+    // device orientation are drawn once per batch, so that no two batches are identical
+    pub fn next_batch(&self, rng: &mut Rng, class: Classification, batch_size: usize) -> Vec<AccelerometerData> {
+        let profile = activity_profile(class);
+
+        let frequency = profile.gait_frequency_hz * (1.0 + 0.1 * symmetric(rng));
+        let gain = 1.0 + 0.2 * symmetric(rng);
+        let mut phase = [0.0; 3];
+        let mut gravity = profile.gravity;
+        for axis in 0..3 {
+            phase[axis] = 2.0 * std::f64::consts::PI * rng.rand() as f64;
+            gravity[axis] += 0.5 * gaussian(rng);
+        }
+
+        let mut batch = Vec::with_capacity(batch_size);
+
+        for i in 0..batch_size {
+            let t = i as f64 / self.sample_rate_hz;
+            let mut sample = [0.0; 3];
+
+            for axis in 0..3 {
+                let angle = 2.0 * std::f64::consts::PI * frequency * t + phase[axis];
+                let periodic = angle.sin() + 0.3 * (2.0 * angle).sin();          // Fundamental plus heel-strike harmonic
+                let value = gravity[axis]
+                    + gain * profile.amplitude[axis] * periodic
+                    + profile.noise_std_dev * gaussian(rng);
+
+                sample[axis] = value.clamp(-SATURATION, SATURATION);
+            }
+
+            batch.push(AccelerometerData {
+                x: sample[0],
+                y: sample[1],
+                z: sample[2],
+            });
+        }
+
+        batch
+    }
+}
+
+// Uniform value in [-1, 1)
+fn symmetric(rng: &mut Rng) -> f64 {
+    2.0 * rng.rand() as f64 - 1.0
+}
+
+// Standard normal value (Box-Muller)
+fn gaussian(rng: &mut Rng) -> f64 {
+    let u1 = (1.0 - rng.rand() as f64).max(f64::MIN_POSITIVE);         // (0, 1], keeps ln() finite even when a draw rounds up to 1
+    let u2 = rng.rand() as f64;
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batches_have_the_requested_size() {
+        let generator = SyntheticGenerator::new(20.0);
+        let mut rng = Rng::new(1);
+        for batch_size in [0, 1, 100, 250] {
+            assert_eq!(generator.next_batch(&mut rng, Classification::Walking, batch_size).len(), batch_size);
+        }
+    }
+
+    #[test]
+    fn samples_are_clipped_at_saturation() {
+        let generator = SyntheticGenerator::new(20.0);
+        let mut rng = Rng::new(2);
+        let values: Vec<f64> = (0..20)
+            .flat_map(|_| generator.next_batch(&mut rng, Classification::Jogging, 200))
+            .flat_map(|sample| [sample.x, sample.y, sample.z])
+            .collect();
+
+        assert!(values.iter().all(|value| value.abs() <= SATURATION));
+        assert!(values.iter().any(|value| value.abs() == SATURATION), "Jogging never reaches the saturation");
+    }
+
+    #[test]
+    fn batches_vary_but_are_reproducible() {
+        let generator = SyntheticGenerator::new(20.0);
+        let batches = |seed: u32| -> Vec<Vec<AccelerometerData>> {
+            let mut rng = Rng::new(seed);
+            (0..2).map(|_| generator.next_batch(&mut rng, Classification::Sitting, 100)).collect()
+        };
+
+        let first_run = batches(3);
+        assert_ne!(first_run[0], first_run[1], "Two batches of the same activity are identical");
+        assert_eq!(first_run, batches(3), "Same seed, different batches");
+    }
+}
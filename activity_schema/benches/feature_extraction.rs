@@ -0,0 +1,102 @@
+// Compares the streaming feature extraction with the statrs based one it replaced in extract_features.
+// Run with `cargo bench` from the activity_schema directory
+use activity_schema::streaming::{FeatureExtractor, PercentileMode};
+use activity_schema::{AccelerometerData, AxisStats, Features};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use statrs::statistics::{OrderStatistics, Statistics};
+
+// Previous implementation of extract_features::handle_cast, kept as the baseline
+fn statrs_features(accelerometer_data: &[AccelerometerData]) -> Features {
+    let mut magnitudes: Vec<f64> = accelerometer_data
+        .iter()
+        .map(|data| ((data.x.powi(2)) + (data.y.powi(2)) + (data.z.powi(2))).sqrt())
+        .collect();
+
+    let mean_mag = magnitudes.clone().mean();
+    let std_dev_mag = magnitudes.clone().std_dev();
+    let min_mag = magnitudes.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max_mag = magnitudes.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let mut coeff_var_mag = 0.0;
+    if mean_mag != 0.0 {
+        coeff_var_mag = std_dev_mag / mean_mag;
+    }
+    let percentile_25_mag = magnitudes.percentile(25);
+    let percentile_75_mag = magnitudes.percentile(75);
+
+    let mut x_vals: Vec<f64> = accelerometer_data.iter().map(|data| data.x).collect();
+    let mut y_vals: Vec<f64> = accelerometer_data.iter().map(|data| data.y).collect();
+    let mut z_vals: Vec<f64> = accelerometer_data.iter().map(|data| data.z).collect();
+
+    fn compute_stats(axis_data: &mut Vec<f64>) -> AxisStats {
+        let mean = axis_data.mean();
+        let std_dev = axis_data.std_dev();
+        let min = axis_data.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = axis_data.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let mut coeff_var = 0.0;
+        if mean != 0.0 {
+            coeff_var = std_dev / mean;
+        }
+        let percentile_25 = axis_data.percentile(25);
+        let percentile_75 = axis_data.percentile(75);
+
+        AxisStats { mean, std_dev, min, max, coeff_var, percentile_25, percentile_75 }
+    }
+
+    let mag = AxisStats {
+        mean: mean_mag,
+        std_dev: std_dev_mag,
+        min: min_mag,
+        max: max_mag,
+        coeff_var: coeff_var_mag,
+        percentile_25: percentile_25_mag,
+        percentile_75: percentile_75_mag,
+    };
+
+    Features::from_axes(mag, compute_stats(&mut x_vals), compute_stats(&mut y_vals), compute_stats(&mut z_vals))
+}
+
+// Deterministic walking-like signal
+fn window(len: usize) -> Vec<AccelerometerData> {
+    (0..len)
+        .map(|i| {
+            let t = i as f64 / 20.0;
+            AccelerometerData {
+                x: 11.35 + 5.8 * (2.0 * std::f64::consts::PI * 1.8 * t).sin() + 0.37 * ((i * 7919) % 13) as f64,
+                y: -1.08 + 2.2 * (2.0 * std::f64::consts::PI * 1.8 * t + 1.0).sin(),
+                z: 0.67 + 4.4 * (2.0 * std::f64::consts::PI * 3.6 * t).cos() - 0.21 * ((i * 104729) % 11) as f64,
+            }
+        })
+        .collect()
+}
+
+fn feature_extraction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("feature_extraction");
+
+    for len in [100, 500, 2000] {
+        let samples = window(len);
+
+        // The comparison is only meaningful if exact mode computes the very same features
+        let mut exact = FeatureExtractor::new(PercentileMode::Exact);
+        let expected = statrs_features(&samples).to_array().map(f64::to_bits);
+        assert_eq!(exact.extract(&samples).to_array().map(f64::to_bits), expected, "Exact mode diverges from statrs");
+
+        group.bench_with_input(BenchmarkId::new("statrs", len), &samples, |b, samples| {
+            b.iter(|| statrs_features(black_box(samples)))
+        });
+
+        let mut exact = FeatureExtractor::new(PercentileMode::Exact);
+        group.bench_with_input(BenchmarkId::new("streaming_exact", len), &samples, |b, samples| {
+            b.iter(|| exact.extract(black_box(samples)))
+        });
+
+        let mut p2 = FeatureExtractor::new(PercentileMode::P2);
+        group.bench_with_input(BenchmarkId::new("streaming_p2", len), &samples, |b, samples| {
+            b.iter(|| p2.extract(black_box(samples)))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, feature_extraction);
+criterion_main!(benches);
@@ -0,0 +1,49 @@
+use serde::{Serialize, Deserialize};
+
+pub const NUM_CLASSES: usize = 5;
+
+// Activity labels. The discriminant is the label the classifier is trained with. Unknown is never
+// trained nor generated: classify returns it when the prediction is not confident enough
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Classification {
+    Jogging = 0,
+    Walking = 1,
+    Standing = 2,
+    Stairs = 3,
+    Sitting = 4,
+    Unknown = 5,
+}
+
+impl Classification {
+    pub const ALL: [Classification; NUM_CLASSES] = [
+        Classification::Jogging,
+        Classification::Walking,
+        Classification::Standing,
+        Classification::Stairs,
+        Classification::Sitting,
+    ];
+
+    pub fn from_label(label: u32) -> Option<Classification> {
+        Classification::ALL.get(label as usize).copied()
+    }
+
+    pub fn label(self) -> u32 {
+        self as u32
+    }
+
+    // Activity names as they appear in the dataset used by classifier_training
+    pub fn from_activity(activity: &str) -> Option<Classification> {
+        Classification::ALL.into_iter().find(|class| class.activity() == activity)
+    }
+
+    pub fn activity(self) -> &'static str {
+        match self {
+            Classification::Jogging => "jogging",
+            Classification::Walking => "walking",
+            Classification::Standing => "standing",
+            Classification::Stairs => "stairs",
+            Classification::Sitting => "sitting",
+            Classification::Unknown => "unknown",
+        }
+    }
+}
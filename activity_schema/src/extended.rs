@@ -0,0 +1,110 @@
+use crate::{AccelerometerData, ExtendedFeatures, ExtendedStats};
+
+// Extended time-domain features of a window: higher moments, zero-crossing rate and jerk of the
+// magnitude and of each axis, signal magnitude area and correlations between the axes.
+// Shared by extract_features and classifier_training, so that models are trained on the same features.
+// Two passes over the window (means first), without allocating. Skewness, kurtosis and correlations
+// of constant signals are 0, and windows shorter than 2 samples give all zeros, as for the spectral features
+pub fn extended_features(samples: &[AccelerometerData], sample_rate_hz: f64) -> ExtendedFeatures {
+    let channels: [fn(&AccelerometerData) -> f64; 4] = [
+        |sample| sample.magnitude(),
+        |sample| sample.x,
+        |sample| sample.y,
+        |sample| sample.z,
+    ];
+    let [mag, x, y, z] = channels.map(|channel| extended_stats(samples, channel, sample_rate_hz));
+
+    if samples.len() < 2 {
+        return ExtendedFeatures::from_signals(mag, x, y, z, 0.0, [0.0; 3]);
+    }
+
+    // Mean of the sum of the absolute accelerations
+    let signal_magnitude_area = samples
+        .iter()
+        .map(|sample| libm::fabs(sample.x) + libm::fabs(sample.y) + libm::fabs(sample.z))
+        .sum::<f64>()
+        / samples.len() as f64;
+
+    let correlations = [
+        correlation(samples, channels[1], channels[2]),
+        correlation(samples, channels[1], channels[3]),
+        correlation(samples, channels[2], channels[3]),
+    ];
+
+    ExtendedFeatures::from_signals(mag, x, y, z, signal_magnitude_area, correlations)
+}
+
+fn extended_stats(samples: &[AccelerometerData], channel: fn(&AccelerometerData) -> f64, sample_rate_hz: f64) -> ExtendedStats {
+    let mut stats = ExtendedStats {
+        skewness: 0.0,
+        kurtosis: 0.0,
+        zero_crossing_rate: 0.0,
+        mean_abs_jerk: 0.0,
+        std_dev_jerk: 0.0,
+    };
+
+    let n = samples.len();
+    if n < 2 {
+        return stats;
+    }
+
+    let mean = samples.iter().map(channel).sum::<f64>() / n as f64;
+    // The mean jerk only depends on the first and last samples
+    let mean_jerk = (channel(&samples[n - 1]) - channel(&samples[0])) * sample_rate_hz / (n - 1) as f64;
+
+    let (mut m2, mut m3, mut m4) = (0.0, 0.0, 0.0);
+    let mut crossings = 0;
+    let (mut abs_jerk_sum, mut jerk_variance_sum) = (0.0, 0.0);
+    let mut previous: Option<f64> = None;
+    for sample in samples {
+        let value = channel(sample);
+        let deviation = value - mean;
+        let squared = deviation * deviation;
+        m2 += squared;
+        m3 += squared * deviation;
+        m4 += squared * squared;
+
+        if let Some(previous) = previous {
+            if (previous - mean) * deviation < 0.0 {
+                crossings += 1;
+            }
+            let jerk = (value - previous) * sample_rate_hz;
+            abs_jerk_sum += libm::fabs(jerk);
+            jerk_variance_sum += (jerk - mean_jerk) * (jerk - mean_jerk);
+        }
+        previous = Some(value);
+    }
+
+    // Population moments
+    let (m2, m3, m4) = (m2 / n as f64, m3 / n as f64, m4 / n as f64);
+    if m2 > 0.0 {
+        stats.skewness = m3 / (m2 * libm::sqrt(m2));
+        stats.kurtosis = m4 / (m2 * m2) - 3.0;
+    }
+    stats.zero_crossing_rate = crossings as f64 / (n - 1) as f64;
+    stats.mean_abs_jerk = abs_jerk_sum / (n - 1) as f64;
+    stats.std_dev_jerk = libm::sqrt(jerk_variance_sum / (n - 1) as f64);
+
+    stats
+}
+
+// Pearson correlation of two channels, 0 if one of them is constant
+fn correlation(samples: &[AccelerometerData], a: fn(&AccelerometerData) -> f64, b: fn(&AccelerometerData) -> f64) -> f64 {
+    let n = samples.len() as f64;
+    let mean_a = samples.iter().map(a).sum::<f64>() / n;
+    let mean_b = samples.iter().map(b).sum::<f64>() / n;
+
+    let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+    for sample in samples {
+        let (deviation_a, deviation_b) = (a(sample) - mean_a, b(sample) - mean_b);
+        covariance += deviation_a * deviation_b;
+        variance_a += deviation_a * deviation_a;
+        variance_b += deviation_b * deviation_b;
+    }
+
+    if variance_a > 0.0 && variance_b > 0.0 {
+        covariance / libm::sqrt(variance_a * variance_b)
+    } else {
+        0.0
+    }
+}
@@ -0,0 +1,376 @@
+use alloc::vec::Vec;
+use serde::{Serialize, Deserialize};
+
+pub const NUM_FEATURES: usize = 28;
+pub const NUM_EXTENDED_FEATURES: usize = 24;
+pub const NUM_SPECTRAL_FEATURES: usize = 28;
+
+// Band power ranges in Hz, the last one extends up to the Nyquist frequency
+pub const BANDS_HZ: [(f64, f64); 4] = [(0.0, 1.0), (1.0, 3.0), (3.0, 5.0), (5.0, f64::INFINITY)];
+
+// Declares a group of f64 features along with the names of its fields. The declaration order
+// is the column order of the feature vector, for training as well as for classification
+macro_rules! feature_group {
+    ($(#[$meta:meta])* $name:ident [$len:expr] { $($field:ident,)* }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        pub struct $name {
+            $(
+                #[serde(deserialize_with = "crate::messages::non_finite_as_nan")]
+                pub $field: f64,
+            )*
+        }
+
+        impl $name {
+            pub const NAMES: [&'static str; $len] = [$(stringify!($field),)*];
+
+            pub fn to_array(&self) -> [f64; $len] {
+                [$(self.$field,)*]
+            }
+        }
+    };
+}
+
+feature_group! {
+    // Time-domain statistics of the magnitude and of each axis
+    Features [NUM_FEATURES] {
+        mean_mag,
+        std_dev_mag,
+        min_mag,
+        max_mag,
+        coeff_var_mag,
+        percentile_25_mag,
+        percentile_75_mag,
+
+        mean_x,
+        std_dev_x,
+        min_x,
+        max_x,
+        coeff_var_x,
+        percentile_25_x,
+        percentile_75_x,
+
+        mean_y,
+        std_dev_y,
+        min_y,
+        max_y,
+        coeff_var_y,
+        percentile_25_y,
+        percentile_75_y,
+
+        mean_z,
+        std_dev_z,
+        min_z,
+        max_z,
+        coeff_var_z,
+        percentile_25_z,
+        percentile_75_z,
+    }
+}
+
+feature_group! {
+    // Optional extended time-domain features, enabled with "extended_features=true" in extract_features
+    ExtendedFeatures [NUM_EXTENDED_FEATURES] {
+        skewness_mag,
+        kurtosis_mag,
+        zero_crossing_rate_mag,
+        mean_abs_jerk_mag,
+        std_dev_jerk_mag,
+
+        skewness_x,
+        kurtosis_x,
+        zero_crossing_rate_x,
+        mean_abs_jerk_x,
+        std_dev_jerk_x,
+
+        skewness_y,
+        kurtosis_y,
+        zero_crossing_rate_y,
+        mean_abs_jerk_y,
+        std_dev_jerk_y,
+
+        skewness_z,
+        kurtosis_z,
+        zero_crossing_rate_z,
+        mean_abs_jerk_z,
+        std_dev_jerk_z,
+
+        signal_magnitude_area,
+        correlation_xy,
+        correlation_xz,
+        correlation_yz,
+    }
+}
+
+feature_group! {
+    // Optional frequency-domain features, enabled with "spectral_features=true" in extract_features
+    SpectralFeatures [NUM_SPECTRAL_FEATURES] {
+        dominant_freq_mag,
+        spectral_energy_mag,
+        spectral_entropy_mag,
+        band_power_0_1hz_mag,
+        band_power_1_3hz_mag,
+        band_power_3_5hz_mag,
+        band_power_5hz_up_mag,
+
+        dominant_freq_x,
+        spectral_energy_x,
+        spectral_entropy_x,
+        band_power_0_1hz_x,
+        band_power_1_3hz_x,
+        band_power_3_5hz_x,
+        band_power_5hz_up_x,
+
+        dominant_freq_y,
+        spectral_energy_y,
+        spectral_entropy_y,
+        band_power_0_1hz_y,
+        band_power_1_3hz_y,
+        band_power_3_5hz_y,
+        band_power_5hz_up_y,
+
+        dominant_freq_z,
+        spectral_energy_z,
+        spectral_entropy_z,
+        band_power_0_1hz_z,
+        band_power_1_3hz_z,
+        band_power_3_5hz_z,
+        band_power_5hz_up_z,
+    }
+}
+
+// Time-domain statistics of one signal
+#[derive(Debug, Clone, Copy)]
+pub struct AxisStats {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+    pub coeff_var: f64,
+    pub percentile_25: f64,
+    pub percentile_75: f64,
+}
+
+// Extended time-domain statistics of one signal
+#[derive(Debug, Clone, Copy)]
+pub struct ExtendedStats {
+    pub skewness: f64,
+    pub kurtosis: f64,                  // Excess kurtosis, 0 for a normal distribution
+    pub zero_crossing_rate: f64,        // Sign changes of the signal minus its mean, per pair of consecutive samples
+    pub mean_abs_jerk: f64,             // Jerk: derivative of the signal, per second
+    pub std_dev_jerk: f64,
+}
+
+// Frequency-domain statistics of one signal
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SpectralStats {
+    pub dominant_freq: f64,             // Frequency of the strongest bin, in Hz
+    pub spectral_energy: f64,           // Sum of the one-sided power spectrum
+    pub spectral_entropy: f64,          // Shannon entropy of the normalized power spectrum, scaled to [0, 1]
+    pub band_power: [f64; 4],           // Power within each of BANDS_HZ
+}
+
+impl Features {
+    pub fn from_axes(mag: AxisStats, x: AxisStats, y: AxisStats, z: AxisStats) -> Features {
+        Features {
+            mean_mag: mag.mean,
+            std_dev_mag: mag.std_dev,
+            min_mag: mag.min,
+            max_mag: mag.max,
+            coeff_var_mag: mag.coeff_var,
+            percentile_25_mag: mag.percentile_25,
+            percentile_75_mag: mag.percentile_75,
+
+            mean_x: x.mean,
+            std_dev_x: x.std_dev,
+            min_x: x.min,
+            max_x: x.max,
+            coeff_var_x: x.coeff_var,
+            percentile_25_x: x.percentile_25,
+            percentile_75_x: x.percentile_75,
+
+            mean_y: y.mean,
+            std_dev_y: y.std_dev,
+            min_y: y.min,
+            max_y: y.max,
+            coeff_var_y: y.coeff_var,
+            percentile_25_y: y.percentile_25,
+            percentile_75_y: y.percentile_75,
+
+            mean_z: z.mean,
+            std_dev_z: z.std_dev,
+            min_z: z.min,
+            max_z: z.max,
+            coeff_var_z: z.coeff_var,
+            percentile_25_z: z.percentile_25,
+            percentile_75_z: z.percentile_75,
+        }
+    }
+}
+
+impl ExtendedFeatures {
+    pub fn from_signals(
+        mag: ExtendedStats,
+        x: ExtendedStats,
+        y: ExtendedStats,
+        z: ExtendedStats,
+        signal_magnitude_area: f64,
+        correlations: [f64; 3],         // xy, xz, yz
+    ) -> ExtendedFeatures {
+        ExtendedFeatures {
+            skewness_mag: mag.skewness,
+            kurtosis_mag: mag.kurtosis,
+            zero_crossing_rate_mag: mag.zero_crossing_rate,
+            mean_abs_jerk_mag: mag.mean_abs_jerk,
+            std_dev_jerk_mag: mag.std_dev_jerk,
+
+            skewness_x: x.skewness,
+            kurtosis_x: x.kurtosis,
+            zero_crossing_rate_x: x.zero_crossing_rate,
+            mean_abs_jerk_x: x.mean_abs_jerk,
+            std_dev_jerk_x: x.std_dev_jerk,
+
+            skewness_y: y.skewness,
+            kurtosis_y: y.kurtosis,
+            zero_crossing_rate_y: y.zero_crossing_rate,
+            mean_abs_jerk_y: y.mean_abs_jerk,
+            std_dev_jerk_y: y.std_dev_jerk,
+
+            skewness_z: z.skewness,
+            kurtosis_z: z.kurtosis,
+            zero_crossing_rate_z: z.zero_crossing_rate,
+            mean_abs_jerk_z: z.mean_abs_jerk,
+            std_dev_jerk_z: z.std_dev_jerk,
+
+            signal_magnitude_area,
+            correlation_xy: correlations[0],
+            correlation_xz: correlations[1],
+            correlation_yz: correlations[2],
+        }
+    }
+}
+
+impl SpectralFeatures {
+    pub fn from_signals(mag: SpectralStats, x: SpectralStats, y: SpectralStats, z: SpectralStats) -> SpectralFeatures {
+        SpectralFeatures {
+            dominant_freq_mag: mag.dominant_freq,
+            spectral_energy_mag: mag.spectral_energy,
+            spectral_entropy_mag: mag.spectral_entropy,
+            band_power_0_1hz_mag: mag.band_power[0],
+            band_power_1_3hz_mag: mag.band_power[1],
+            band_power_3_5hz_mag: mag.band_power[2],
+            band_power_5hz_up_mag: mag.band_power[3],
+
+            dominant_freq_x: x.dominant_freq,
+            spectral_energy_x: x.spectral_energy,
+            spectral_entropy_x: x.spectral_entropy,
+            band_power_0_1hz_x: x.band_power[0],
+            band_power_1_3hz_x: x.band_power[1],
+            band_power_3_5hz_x: x.band_power[2],
+            band_power_5hz_up_x: x.band_power[3],
+
+            dominant_freq_y: y.dominant_freq,
+            spectral_energy_y: y.spectral_energy,
+            spectral_entropy_y: y.spectral_entropy,
+            band_power_0_1hz_y: y.band_power[0],
+            band_power_1_3hz_y: y.band_power[1],
+            band_power_3_5hz_y: y.band_power[2],
+            band_power_5hz_up_y: y.band_power[3],
+
+            dominant_freq_z: z.dominant_freq,
+            spectral_energy_z: z.spectral_energy,
+            spectral_entropy_z: z.spectral_entropy,
+            band_power_0_1hz_z: z.band_power[0],
+            band_power_1_3hz_z: z.band_power[1],
+            band_power_3_5hz_z: z.band_power[2],
+            band_power_5hz_up_z: z.band_power[3],
+        }
+    }
+}
+
+// Optional feature groups of a feature vector, computed on top of the time-domain features
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FeatureGroups {
+    pub extended: bool,
+    pub spectral: bool,
+    pub body: bool,                     // All features computed on the body acceleration, gravity removed
+}
+
+impl FeatureGroups {
+    // Parses the names of the enabled groups ("extended", "spectral", "body"), None if one is unknown
+    pub fn from_names<'a>(names: impl IntoIterator<Item = &'a str>) -> Option<FeatureGroups> {
+        let mut groups = FeatureGroups::default();
+        for name in names {
+            match name.trim() {
+                "extended" => groups.extended = true,
+                "spectral" => groups.spectral = true,
+                "body" => groups.body = true,
+                "" => {}
+                _ => return None,
+            }
+        }
+        Some(groups)
+    }
+}
+
+// Feature columns as fed to the classifier: the time-domain features, then the extended and the
+// spectral ones if any
+pub fn feature_vector(
+    features: &Features,
+    extended_features: Option<&ExtendedFeatures>,
+    spectral_features: Option<&SpectralFeatures>,
+) -> Vec<f64> {
+    let mut vector = Vec::with_capacity(NUM_FEATURES + NUM_EXTENDED_FEATURES + NUM_SPECTRAL_FEATURES);
+    vector.extend(features.to_array());
+    if let Some(extended_features) = extended_features {
+        vector.extend(extended_features.to_array());
+    }
+    if let Some(spectral_features) = spectral_features {
+        vector.extend(spectral_features.to_array());
+    }
+    vector
+}
+
+pub fn feature_names(groups: FeatureGroups) -> Vec<&'static str> {
+    let mut names = Vec::from(Features::NAMES);
+    if groups.extended {
+        names.extend(ExtendedFeatures::NAMES);
+    }
+    if groups.spectral {
+        names.extend(SpectralFeatures::NAMES);
+    }
+    names
+}
+
+// FNV-1a hash of the ordered feature names. Stored with the trained model and carried by the
+// features payload, so that classify can refuse features that do not match the model.
+// Features of the body acceleration have the same names, a marker tells them apart
+pub const fn schema_hash(groups: FeatureGroups) -> u64 {
+    let mut hash = hash_names(0xcbf29ce484222325, &Features::NAMES);
+    if groups.extended {
+        hash = hash_names(hash, &ExtendedFeatures::NAMES);
+    }
+    if groups.spectral {
+        hash = hash_names(hash, &SpectralFeatures::NAMES);
+    }
+    if groups.body {
+        hash = hash_names(hash, &["@body"]);
+    }
+    hash
+}
+
+const fn hash_names(mut hash: u64, names: &[&str]) -> u64 {
+    let mut i = 0;
+    while i < names.len() {
+        let bytes = names[i].as_bytes();
+        let mut j = 0;
+        while j <= bytes.len() {
+            let byte = if j < bytes.len() { bytes[j] } else { b',' };          // Names are comma-terminated
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+            j += 1;
+        }
+        i += 1;
+    }
+    hash
+}
@@ -0,0 +1,87 @@
+// Golden feature vectors: recorded windows along with the features expected for them, in
+// tests/fixtures/feature_vectors.json. The sample tables of generate_samples, the on-device extraction
+// and the trainer's are all checked against them in `cargo test`, so that any divergence between
+// them (field order, percentile interpolation, handling of degenerate windows) fails the build.
+// The expected values come from the statrs based extraction the trainer uses, and must only change
+// along with a deliberate change of the features (and a retraining of the model)
+use std::string::String;
+use std::vec::Vec;
+
+use serde::Deserialize;
+
+use crate::{feature_names, AccelerometerData, FeatureGroups};
+
+const FIXTURE: &str = include_str!("../tests/fixtures/feature_vectors.json");
+
+pub struct GoldenWindow {
+    pub name: String,                       // "generate_samples/<activity>" for the tables of generate_samples
+    pub samples: Vec<AccelerometerData>,
+    pub features: Vec<(String, f64)>,       // In the column order of feature_vector()
+}
+
+#[derive(Deserialize)]
+struct Fixture {
+    windows: Vec<RawWindow>,
+}
+
+#[derive(Deserialize)]
+struct RawWindow {
+    name: String,
+    samples: Vec<[f64; 3]>,
+    features: Vec<(String, serde_json::Value)>,
+}
+
+pub fn golden_windows() -> Vec<GoldenWindow> {
+    let fixture: Fixture = serde_json::from_str(FIXTURE).expect("Invalid golden feature vectors fixture");
+
+    fixture
+        .windows
+        .into_iter()
+        .map(|window| GoldenWindow {
+            samples: window.samples.iter().map(|&[x, y, z]| AccelerometerData { x, y, z }).collect(),
+            features: window
+                .features
+                .into_iter()
+                .map(|(name, value)| {
+                    // JSON has no NaN, it is written as a string
+                    let value = match value {
+                        serde_json::Value::String(text) if text == "NaN" => f64::NAN,
+                        value => value.as_f64().unwrap_or_else(|| panic!("Invalid value of {} in {}", name, window.name)),
+                    };
+                    (name, value)
+                })
+                .collect(),
+            name: window.name,
+        })
+        .collect()
+}
+
+impl GoldenWindow {
+    // Compares a feature vector computed for this window with the expected one, bit for bit
+    // (any NaN matching any NaN), naming every feature that differs
+    pub fn check(&self, computed: &[f64]) -> Result<(), String> {
+        let expected_names: Vec<&str> = self.features.iter().map(|(name, _)| name.as_str()).collect();
+        if expected_names != feature_names(FeatureGroups::default()) {
+            return Err(format!("{}: fixture columns {:?} are not those of feature_names()", self.name, expected_names));
+        }
+        if computed.len() != self.features.len() {
+            return Err(format!("{}: {} features computed, {} expected", self.name, computed.len(), self.features.len()));
+        }
+
+        let mismatches: Vec<String> = self
+            .features
+            .iter()
+            .zip(computed)
+            .filter(|((_, expected), computed)| {
+                !(expected.to_bits() == computed.to_bits() || (expected.is_nan() && computed.is_nan()))
+            })
+            .map(|((name, expected), computed)| format!("{} = {:e}, expected {:e}", name, computed, expected))
+            .collect();
+
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("{}: {}", self.name, mismatches.join(", ")))
+        }
+    }
+}
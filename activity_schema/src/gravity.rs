@@ -0,0 +1,56 @@
+use alloc::vec::Vec;
+
+use crate::AccelerometerData;
+
+// Default cutoff of the gravity low-pass filter: gravity changes with the orientation of the
+// device only, body motion lies above
+pub const GRAVITY_CUTOFF_HZ: f64 = 0.3;
+
+// Splits gravity from body acceleration: gravity is estimated with a first-order low-pass IIR filter
+// of each axis, the body component is what remains (a high-pass filter). Shared by extract_features,
+// with one filter per device whose state carries over between its consecutive batches, and by
+// classifier_training, which runs it over the continuous stream of samples of each activity
+pub struct GravityFilter {
+    alpha: f64,                             // Smoothing factor of the low-pass filter
+    gravity: Option<[f64; 3]>,              // Current estimate, None until the first finite sample
+    tail: Vec<AccelerometerData>,           // Body component of the previous batch, for the samples it shares with the next one
+}
+
+impl GravityFilter {
+    pub fn new(cutoff_hz: f64, sample_rate_hz: f64) -> GravityFilter {
+        GravityFilter {
+            alpha: 1.0 - libm::exp(-2.0 * core::f64::consts::PI * cutoff_hz / sample_rate_hz),
+            gravity: None,
+            tail: Vec::new(),
+        }
+    }
+
+    // Replaces the samples with their body component. The first `overlap` samples are the last ones
+    // of the previous batch (overlapping windows): they have been filtered already and are given the
+    // same body component, the filter only advancing over the new samples. Non-finite readings are
+    // left out of the estimate, so that a faulty sample does not corrupt the following batches
+    pub fn separate(&mut self, samples: &mut [AccelerometerData], overlap: usize) {
+        let overlap = overlap.min(samples.len()).min(self.tail.len());
+        let reused = self.tail.len() - overlap;
+        samples[..overlap].clone_from_slice(&self.tail[reused..]);
+
+        for sample in samples[overlap..].iter_mut() {
+            let reading = [sample.x, sample.y, sample.z];
+            if reading.iter().all(|value| value.is_finite()) {
+                let gravity = self.gravity.get_or_insert(reading);
+                for (estimate, value) in gravity.iter_mut().zip(reading) {
+                    *estimate += self.alpha * (value - *estimate);
+                }
+            }
+
+            // No estimate yet only until the first finite reading, non-finite ones stay so
+            let gravity = self.gravity.unwrap_or(reading);
+            sample.x -= gravity[0];
+            sample.y -= gravity[1];
+            sample.z -= gravity[2];
+        }
+
+        self.tail.clear();
+        self.tail.extend_from_slice(samples);
+    }
+}
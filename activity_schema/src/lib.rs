@@ -0,0 +1,32 @@
+// Types shared by the functions of the activity recognition workflow and by classifier_training:
+// the messages exchanged along the workflow, the ordered feature vector and the activity labels.
+// Defining them once makes any drift between the producer and the consumer of a message a compile error.
+// no_std (with alloc) unless the "std" feature is enabled, so that it can be used on constrained targets
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod classification;
+pub mod extended;
+mod features;
+#[cfg(feature = "golden")]
+pub mod golden;
+pub mod gravity;
+mod messages;
+#[cfg(feature = "models")]
+pub mod models;
+#[cfg(feature = "spectral")]
+pub mod spectral;
+pub mod streaming;
+pub mod timing;
+#[cfg(feature = "wire")]
+pub mod wire;
+
+pub use classification::{Classification, NUM_CLASSES};
+pub use features::{
+    feature_names, feature_vector, schema_hash, AxisStats, ExtendedFeatures, ExtendedStats, FeatureGroups, Features,
+    SpectralFeatures, SpectralStats, BANDS_HZ, NUM_EXTENDED_FEATURES, NUM_FEATURES, NUM_SPECTRAL_FEATURES,
+};
+pub use messages::{
+    sample_sequences, AccelerometerData, ClassificationPayload, FeaturesPayload, HopTimestamps, SamplesPayload, WindowId,
+};
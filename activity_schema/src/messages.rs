@@ -0,0 +1,143 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use serde::{Serialize, Deserialize};
+
+use crate::{Classification, ExtendedFeatures, Features, SpectralFeatures, NUM_CLASSES};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccelerometerData {
+    #[serde(deserialize_with = "non_finite_as_nan")]
+    pub x: f64,
+    #[serde(deserialize_with = "non_finite_as_nan")]
+    pub y: f64,
+    #[serde(deserialize_with = "non_finite_as_nan")]
+    pub z: f64,
+}
+
+impl AccelerometerData {
+    pub fn magnitude(&self) -> f64 {
+        libm::sqrt(self.x * self.x + self.y * self.y + self.z * self.z)
+    }
+}
+
+// JSON has no NaN/Inf: serde_json writes them as null, read back here as NaN. Used by the samples and
+// by the features, which are NaN for faulty or empty windows. Binary encodings carry them as they are
+pub(crate) fn non_finite_as_nan<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    if deserializer.is_human_readable() {
+        Ok(Option::<f64>::deserialize(deserializer)?.unwrap_or(f64::NAN))
+    } else {
+        f64::deserialize(deserializer)
+    }
+}
+
+// Receive and send time of a payload at one stage of the workflow
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HopTimestamps {
+    pub stage: String,
+    pub received_us: Option<u64>,
+    pub sent_us: Option<u64>,
+}
+
+// A batch may complete several windows, or none: each window classified is identified by its batch
+// and its index among the windows the batch completed. Written "<batch_id>" to the logs for the first
+// window of a batch, the id its TTC measurement has been started with, "<batch_id>.<window_index>" after it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct WindowId {
+    pub batch_id: u64,
+    pub window_index: u32,
+}
+
+impl fmt::Display for WindowId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.window_index == 0 {
+            write!(f, "{}", self.batch_id)
+        } else {
+            write!(f, "{}.{}", self.batch_id, self.window_index)
+        }
+    }
+}
+
+// Sequence numbers, within the stream of their device, of the samples of a window starting at
+// `first_sequence`, once the samples at the `dropped` positions have been removed
+pub fn sample_sequences(first_sequence: u64, dropped: &[u32], len: usize) -> Vec<u64> {
+    (0u64..)
+        .filter(|position| !dropped.contains(&(*position as u32)))
+        .take(len)
+        .map(|position| first_sequence + position)
+        .collect()
+}
+
+// generate_samples -> extract_features
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SamplesPayload {
+    pub batch_id: u64,
+    pub device_id: u32,
+    pub true_class: Classification,             // Ground truth, scored downstream by handle_class_result
+    pub batch: Vec<AccelerometerData>,
+    #[serde(default)]
+    pub overlap: usize,                         // Leading samples already sent at the end of the device's previous batch
+    #[serde(default)]
+    pub first_sequence: Option<u64>,            // Sequence number of the first sample of the window, None if the producer does not number them
+    #[serde(default)]
+    pub dropped: Vec<u32>,                      // Positions in the window of the samples dropped before sending it
+    #[serde(default)]
+    pub faults: Vec<String>,                    // Sensor faults injected in this batch
+    #[serde(default)]
+    pub hops: Vec<HopTimestamps>,               // One entry per stage, appended by each function
+}
+
+impl SamplesPayload {
+    // Sequence numbers of the samples of the batch, None if the producer does not number them
+    pub fn sequences(&self) -> Option<Vec<u64>> {
+        self.first_sequence
+            .map(|first_sequence| sample_sequences(first_sequence, &self.dropped, self.batch.len()))
+    }
+}
+
+// extract_features -> classify
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FeaturesPayload {
+    pub batch_id: u64,
+    #[serde(default)]
+    pub window_index: u32,                      // Among the windows completed by the batch
+    pub device_id: u32,
+    pub true_class: Classification,
+    pub features: Features,
+    #[serde(default)]
+    pub extended_features: Option<ExtendedFeatures>,
+    #[serde(default)]
+    pub spectral_features: Option<SpectralFeatures>,
+    pub schema_hash: u64,                       // schema_hash() of the features carried
+    #[serde(default)]
+    pub hops: Vec<HopTimestamps>,
+}
+
+impl FeaturesPayload {
+    pub fn window_id(&self) -> WindowId {
+        WindowId { batch_id: self.batch_id, window_index: self.window_index }
+    }
+}
+
+// classify -> handle_class_result
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClassificationPayload {
+    pub batch_id: u64,
+    #[serde(default)]
+    pub window_index: u32,
+    pub device_id: u32,
+    pub true_class: Classification,
+    pub classification: Classification,
+    #[serde(default)]
+    pub model_version: Option<u64>,             // Version of the model that produced the classification, None if stored without one
+    #[serde(default)]
+    pub class_votes: Option<[f64; NUM_CLASSES]>,  // Fraction of the votes each class got, indexed by label
+    #[serde(default)]
+    pub hops: Vec<HopTimestamps>,
+}
+
+impl ClassificationPayload {
+    pub fn window_id(&self) -> WindowId {
+        WindowId { batch_id: self.batch_id, window_index: self.window_index }
+    }
+}
@@ -0,0 +1,315 @@
+// Model families classify can run, shared with classifier_training so that a model is deserialized
+// into the very type it has been trained as. The family is stored along with the model, tagged with
+// its ModelKind, and every family is used through the Classifier trait
+use std::boxed::Box;
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+
+use crate::NUM_CLASSES;
+use smartcore::ensemble::random_forest_classifier::RandomForestClassifier;
+use smartcore::linalg::basic::arrays::Array;
+use smartcore::linalg::basic::matrix::DenseMatrix;
+use smartcore::linear::logistic_regression::LogisticRegression;
+use smartcore::metrics::distance::euclidian::Euclidian;
+use smartcore::naive_bayes::gaussian::GaussianNB;
+use smartcore::neighbors::knn_classifier::KNNClassifier;
+use smartcore::svm::svc::{SVCParameters, SVC};
+use smartcore::svm::Kernels;
+use smartcore::tree::decision_tree_classifier::DecisionTreeClassifier;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelKind {
+    #[default]
+    RandomForest,           // Models stored without a kind are random forests
+    DecisionTree,
+    Knn,
+    GaussianNb,
+    LogisticRegression,
+    LinearSvm,
+}
+
+impl ModelKind {
+    pub const ALL: [ModelKind; 6] = [
+        ModelKind::RandomForest,
+        ModelKind::DecisionTree,
+        ModelKind::Knn,
+        ModelKind::GaussianNb,
+        ModelKind::LogisticRegression,
+        ModelKind::LinearSvm,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ModelKind::RandomForest => "random_forest",
+            ModelKind::DecisionTree => "decision_tree",
+            ModelKind::Knn => "knn",
+            ModelKind::GaussianNb => "gaussian_nb",
+            ModelKind::LogisticRegression => "logistic_regression",
+            ModelKind::LinearSvm => "linear_svm",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ModelKind> {
+        ModelKind::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+// A trained model of any family. Labels are those of Classification
+pub trait Classifier: Send {
+    fn kind(&self) -> ModelKind;
+    fn predict(&self, x: &DenseMatrix<f64>) -> Result<Vec<i32>, String>;
+    fn serialize(&self) -> Result<Vec<u8>, String>;      // bincode, read back by deserialize()
+
+    // Predicted label and fraction of the votes each class gets for each sample, indexed by label, from a
+    // single run of the model. Forests count the votes of their trees, the other families give all of them
+    // to the predicted class
+    fn predict_votes(&self, x: &DenseMatrix<f64>) -> Result<Vec<(i32, [f64; NUM_CLASSES])>, String> {
+        Ok(self
+            .predict(x)?
+            .into_iter()
+            .map(|label| {
+                let mut votes = [0.0; NUM_CLASSES];
+                if let Some(vote) = usize::try_from(label).ok().and_then(|label| votes.get_mut(label)) {
+                    *vote = 1.0;
+                }
+                (label, votes)
+            })
+            .collect())
+    }
+
+    fn class_votes(&self, x: &DenseMatrix<f64>) -> Result<Vec<[f64; NUM_CLASSES]>, String> {
+        Ok(self.predict_votes(x)?.into_iter().map(|(_, votes)| votes).collect())
+    }
+}
+
+// Layout of the serialized models, the same as when only random forests were supported
+#[derive(Serialize, Deserialize)]
+struct SerializableModel<M> {
+    classifier: M,
+}
+
+fn serialize_model<M: Serialize>(classifier: &M) -> Result<Vec<u8>, String> {
+    bincode::serialize(&SerializableModel { classifier }).map_err(|e| e.to_string())
+}
+
+fn deserialize_model<M: serde::de::DeserializeOwned + Classifier + 'static>(bytes: &[u8]) -> Result<Box<dyn Classifier>, String> {
+    let model: SerializableModel<M> = bincode::deserialize(bytes).map_err(|e| e.to_string())?;
+    Ok(Box::new(model.classifier))
+}
+
+// Families whose smartcore estimator predicts the i32 labels it has been trained on
+macro_rules! smartcore_classifier {
+    ($kind:expr, $model:ty) => {
+        impl Classifier for $model {
+            fn kind(&self) -> ModelKind {
+                $kind
+            }
+
+            fn predict(&self, x: &DenseMatrix<f64>) -> Result<Vec<i32>, String> {
+                <$model>::predict(self, x).map_err(|e| e.to_string())
+            }
+
+            fn serialize(&self) -> Result<Vec<u8>, String> {
+                serialize_model(self)
+            }
+        }
+    };
+}
+
+type RandomForest = RandomForestClassifier<f64, i32, DenseMatrix<f64>, Vec<i32>>;
+type DecisionTree = DecisionTreeClassifier<f64, i32, DenseMatrix<f64>, Vec<i32>>;
+type Knn = KNNClassifier<f64, i32, DenseMatrix<f64>, Vec<i32>, Euclidian<f64>>;
+type Logistic = LogisticRegression<f64, i32, DenseMatrix<f64>, Vec<i32>>;
+type NaiveBayes = GaussianNB<f64, u32, DenseMatrix<f64>, Vec<u32>>;        // Unsigned labels only
+
+smartcore_classifier!(ModelKind::DecisionTree, DecisionTree);
+smartcore_classifier!(ModelKind::Knn, Knn);
+smartcore_classifier!(ModelKind::LogisticRegression, Logistic);
+
+impl Classifier for NaiveBayes {
+    fn kind(&self) -> ModelKind {
+        ModelKind::GaussianNb
+    }
+
+    fn predict(&self, x: &DenseMatrix<f64>) -> Result<Vec<i32>, String> {
+        let labels = NaiveBayes::predict(self, x).map_err(|e| e.to_string())?;
+        Ok(labels.into_iter().map(|label| label as i32).collect())
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, String> {
+        serialize_model(self)
+    }
+}
+
+// smartcore's RandomForestClassifier keeps its trees private: forests are read back with the same fields,
+// so that they serialize alike, and the votes of the trees can be counted. Predictions are those of
+// smartcore, the first class in label order winning ties
+#[derive(Serialize, Deserialize)]
+struct Forest {
+    trees: Option<Vec<DecisionTree>>,
+    classes: Option<Vec<i32>>,
+    samples: Option<Vec<Vec<bool>>>,
+}
+
+impl Forest {
+    // Votes of the trees for each sample, in the order of `classes`
+    fn votes(&self, x: &DenseMatrix<f64>) -> Result<(&[i32], Vec<Vec<usize>>), String> {
+        let (trees, classes) = match (&self.trees, &self.classes) {
+            (Some(trees), Some(classes)) => (trees, classes),
+            _ => return Err("Random forest without trees".to_string()),
+        };
+
+        let mut votes = vec![vec![0; classes.len()]; x.shape().0];
+        for tree in trees {
+            let labels = tree.predict(x).map_err(|e| e.to_string())?;
+            for (sample_votes, label) in votes.iter_mut().zip(labels) {
+                if let Some(class) = classes.iter().position(|&class| class == label) {
+                    sample_votes[class] += 1;
+                }
+            }
+        }
+        Ok((classes, votes))
+    }
+}
+
+impl Classifier for Forest {
+    fn kind(&self) -> ModelKind {
+        ModelKind::RandomForest
+    }
+
+    fn predict(&self, x: &DenseMatrix<f64>) -> Result<Vec<i32>, String> {
+        Ok(self.predict_votes(x)?.into_iter().map(|(label, _)| label).collect())
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, String> {
+        serialize_model(self)
+    }
+
+    fn predict_votes(&self, x: &DenseMatrix<f64>) -> Result<Vec<(i32, [f64; NUM_CLASSES])>, String> {
+        let (classes, votes) = self.votes(x)?;
+        Ok(votes
+            .into_iter()
+            .map(|sample_votes| {
+                // Most voted class, the first one in the order of `classes` winning ties as in smartcore
+                let mut best = 0;
+                for (class, &count) in sample_votes.iter().enumerate() {
+                    if count > sample_votes[best] {
+                        best = class;
+                    }
+                }
+
+                let total = sample_votes.iter().sum::<usize>().max(1) as f64;
+                let mut fractions = [0.0; NUM_CLASSES];
+                for (&label, &count) in classes.iter().zip(&sample_votes) {
+                    if let Some(fraction) = usize::try_from(label).ok().and_then(|label| fractions.get_mut(label)) {
+                        *fraction = count as f64 / total;
+                    }
+                }
+                (classes[best], fractions)
+            })
+            .collect())
+    }
+}
+
+// smartcore's SVC separates two classes only, and needs its kernel to predict, which it does not
+// serialize. The linear SVM is therefore trained one-vs-rest with SVC and stored as its hyperplanes
+#[derive(Serialize, Deserialize)]
+struct LinearSvm {
+    classes: Vec<i32>,
+    weights: Vec<Vec<f64>>,         // One hyperplane per class, separating it from the others
+    biases: Vec<f64>,
+}
+
+impl LinearSvm {
+    fn fit(x: &DenseMatrix<f64>, y: &[i32]) -> Result<LinearSvm, String> {
+        let (_, num_features) = x.shape();
+        let mut classes = y.to_vec();
+        classes.sort_unstable();
+        classes.dedup();
+
+        // The zero vector then each unit vector: the decision function of a linear kernel is w·x + b
+        let mut probes = vec![vec![0.0; num_features]];
+        for feature in 0..num_features {
+            let mut probe = vec![0.0; num_features];
+            probe[feature] = 1.0;
+            probes.push(probe);
+        }
+        let probes = DenseMatrix::from_2d_vec(&probes);
+
+        let mut svm = LinearSvm { classes: Vec::new(), weights: Vec::new(), biases: Vec::new() };
+        for class in classes {
+            // SVC only takes the labels 1 and -1, the positive side being that of the class
+            let one_vs_rest: Vec<i32> = y.iter().map(|&label| if label == class { 1 } else { -1 }).collect();
+            let parameters = SVCParameters::default().with_c(1.0).with_kernel(Kernels::linear());
+            let svc = SVC::fit(x, &one_vs_rest, &parameters).map_err(|e| e.to_string())?;
+
+            let decision = svc.decision_function(&probes).map_err(|e| e.to_string())?;
+            svm.classes.push(class);
+            svm.biases.push(decision[0]);
+            svm.weights.push(decision[1..].iter().map(|value| value - decision[0]).collect());
+        }
+
+        Ok(svm)
+    }
+}
+
+impl Classifier for LinearSvm {
+    fn kind(&self) -> ModelKind {
+        ModelKind::LinearSvm
+    }
+
+    // Class of the hyperplane the sample is the farthest on the positive side of
+    fn predict(&self, x: &DenseMatrix<f64>) -> Result<Vec<i32>, String> {
+        let (num_samples, num_features) = x.shape();
+        if self.weights.iter().any(|weights| weights.len() != num_features) {
+            return Err(format!("{} features given to a linear SVM trained on {}", num_features, self.weights[0].len()));
+        }
+
+        Ok((0..num_samples)
+            .map(|sample| {
+                let score = |class: usize| -> f64 {
+                    self.biases[class] + (0..num_features).map(|feature| self.weights[class][feature] * *x.get((sample, feature))).sum::<f64>()
+                };
+                let best = (0..self.classes.len()).max_by(|&a, &b| score(a).total_cmp(&score(b))).unwrap_or(0);
+                self.classes[best]
+            })
+            .collect())
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, String> {
+        serialize_model(self)
+    }
+}
+
+// Trains a model of the given family with smartcore's default parameters
+pub fn train(kind: ModelKind, x: &DenseMatrix<f64>, y: &Vec<i32>) -> Result<Box<dyn Classifier>, String> {
+    Ok(match kind {
+        ModelKind::RandomForest => {
+            let forest = RandomForest::fit(x, y, Default::default()).map_err(|e| e.to_string())?;
+            return deserialize_model::<Forest>(&serialize_model(&forest)?);
+        }
+        ModelKind::DecisionTree => Box::new(DecisionTree::fit(x, y, Default::default()).map_err(|e| e.to_string())?),
+        ModelKind::Knn => Box::new(Knn::fit(x, y, Default::default()).map_err(|e| e.to_string())?),
+        ModelKind::GaussianNb => {
+            let labels: Vec<u32> = y.iter().map(|&label| label as u32).collect();
+            Box::new(NaiveBayes::fit(x, &labels, Default::default()).map_err(|e| e.to_string())?)
+        }
+        ModelKind::LogisticRegression => Box::new(Logistic::fit(x, y, Default::default()).map_err(|e| e.to_string())?),
+        ModelKind::LinearSvm => Box::new(LinearSvm::fit(x, y)?),
+    })
+}
+
+// Reads back a model serialized by Classifier::serialize()
+pub fn deserialize(kind: ModelKind, bytes: &[u8]) -> Result<Box<dyn Classifier>, String> {
+    match kind {
+        ModelKind::RandomForest => deserialize_model::<Forest>(bytes),
+        ModelKind::DecisionTree => deserialize_model::<DecisionTree>(bytes),
+        ModelKind::Knn => deserialize_model::<Knn>(bytes),
+        ModelKind::GaussianNb => deserialize_model::<NaiveBayes>(bytes),
+        ModelKind::LogisticRegression => deserialize_model::<Logistic>(bytes),
+        ModelKind::LinearSvm => deserialize_model::<LinearSvm>(bytes),
+    }
+}
@@ -0,0 +1,120 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use realfft::num_complex::Complex;
+use realfft::{RealFftPlanner, RealToComplex};
+
+use crate::{SpectralStats, BANDS_HZ};
+
+// Frequency-domain statistics of one signal, computed with a real FFT.
+// Shared by extract_features and classifier_training, so that models are trained on the same features
+
+// FFT plan and buffers, kept across the signals and the windows of the same length, the way
+// streaming::FeatureExtractor keeps its scratch buffers. A signal of another length plans again
+pub struct SpectralAnalyzer {
+    planner: RealFftPlanner<f64>,
+    fft: Option<Arc<dyn RealToComplex<f64>>>,       // Plan for the length of the last signal
+    input: Vec<f64>,
+    spectrum: Vec<Complex<f64>>,
+    scratch: Vec<Complex<f64>>,
+}
+
+impl Default for SpectralAnalyzer {
+    fn default() -> SpectralAnalyzer {
+        SpectralAnalyzer::new()
+    }
+}
+
+impl SpectralAnalyzer {
+    pub fn new() -> SpectralAnalyzer {
+        SpectralAnalyzer {
+            planner: RealFftPlanner::new(),
+            fft: None,
+            input: Vec::new(),
+            spectrum: Vec::new(),
+            scratch: Vec::new(),
+        }
+    }
+
+    // The mean (DC component, mostly gravity) is removed before the FFT, so that it does not
+    // dominate the spectrum. Constant signals or signals shorter than 2 samples give all zeros
+    pub fn stats(&mut self, signal: &[f64], sample_rate_hz: f64) -> SpectralStats {
+        let n = signal.len();
+        if n < 2 {
+            return SpectralStats::default();
+        }
+
+        if !matches!(&self.fft, Some(fft) if fft.len() == n) {
+            let fft = self.planner.plan_fft_forward(n);
+            self.input = fft.make_input_vec();
+            self.spectrum = fft.make_output_vec();
+            self.scratch = fft.make_scratch_vec();
+            self.fft = Some(fft);
+        }
+        let fft = self.fft.as_ref().unwrap();
+
+        let mean = signal.iter().sum::<f64>() / n as f64;
+        for (input, value) in self.input.iter_mut().zip(signal) {
+            *input = value - mean;
+        }
+        fft.process_with_scratch(&mut self.input, &mut self.spectrum, &mut self.scratch).unwrap();
+
+        spectrum_stats(&self.spectrum, n, sample_rate_hz)
+    }
+}
+
+// Statistics of a single signal, planning its FFT for it alone
+pub fn spectral_stats(signal: &[f64], sample_rate_hz: f64) -> SpectralStats {
+    SpectralAnalyzer::new().stats(signal, sample_rate_hz)
+}
+
+// Statistics of the one-sided spectrum of a signal of n samples
+fn spectrum_stats(spectrum: &[Complex<f64>], n: usize, sample_rate_hz: f64) -> SpectralStats {
+    let mut stats = SpectralStats::default();
+
+    // One-sided power spectrum without the DC bin
+    let power = || spectrum.iter().skip(1).map(|bin| bin.norm_sqr() / n as f64);
+    let bins = spectrum.len() - 1;
+    let bin_hz = sample_rate_hz / n as f64;
+
+    stats.spectral_energy = power().sum();
+    if stats.spectral_energy.is_nan() {
+        // Non-finite samples, as the time-domain statistics
+        return SpectralStats {
+            dominant_freq: f64::NAN,
+            spectral_energy: f64::NAN,
+            spectral_entropy: f64::NAN,
+            band_power: [f64::NAN; 4],
+        };
+    }
+    if stats.spectral_energy == 0.0 {
+        return stats;
+    }
+
+    let mut dominant_bin = 0;
+    let mut dominant_power = f64::NEG_INFINITY;
+    for (bin, value) in power().enumerate() {
+        if value > dominant_power {
+            dominant_bin = bin;
+            dominant_power = value;
+        }
+
+        let freq = (bin + 1) as f64 * bin_hz;
+        for (band, (low, high)) in BANDS_HZ.iter().enumerate() {
+            if freq >= *low && freq < *high {
+                stats.band_power[band] += value;
+            }
+        }
+
+        let p = value / stats.spectral_energy;
+        if p > 0.0 {
+            stats.spectral_entropy -= p * p.log2();
+        }
+    }
+
+    stats.dominant_freq = (dominant_bin + 1) as f64 * bin_hz;
+    if bins > 1 {
+        stats.spectral_entropy /= (bins as f64).log2();
+    }
+
+    stats
+}
@@ -0,0 +1,342 @@
+use alloc::vec::Vec;
+
+use crate::{AccelerometerData, AxisStats, Features};
+
+// How the 25th and 75th percentiles are computed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PercentileMode {
+    Exact,              // Selection over a copy of the window, bit-compatible with statrs
+    P2,                 // P² estimator (Jain & Chlamtac), constant memory and no copy of the window
+}
+
+impl PercentileMode {
+    pub fn from_name(name: &str) -> Option<PercentileMode> {
+        match name {
+            "exact" => Some(PercentileMode::Exact),
+            "p2" => Some(PercentileMode::P2),
+            _ => None,
+        }
+    }
+}
+
+// Single-pass replacement of the statrs based feature extraction. All statistics are updated
+// sample by sample; in exact mode the values are also copied into scratch buffers kept across
+// windows, so that no allocation happens once the largest window has been seen.
+// In exact mode the features are bit-for-bit those of the previous implementation: statrs'
+// recurrences are reproduced as they are, and so is its selection algorithm for percentiles
+pub struct FeatureExtractor {
+    mode: PercentileMode,
+    scratch: [Vec<f64>; 4],             // Magnitude, x, y, z
+}
+
+impl FeatureExtractor {
+    pub fn new(mode: PercentileMode) -> FeatureExtractor {
+        FeatureExtractor {
+            mode,
+            scratch: [Vec::new(), Vec::new(), Vec::new(), Vec::new()],
+        }
+    }
+
+    pub fn extract(&mut self, samples: &[AccelerometerData]) -> Features {
+        let mut signals = [SignalStats::new(), SignalStats::new(), SignalStats::new(), SignalStats::new()];
+        let mut estimators = [Quartiles::new(), Quartiles::new(), Quartiles::new(), Quartiles::new()];
+        for buffer in self.scratch.iter_mut() {
+            buffer.clear();
+        }
+
+        for sample in samples {
+            let values = [sample.magnitude(), sample.x, sample.y, sample.z];
+
+            for (i, value) in values.into_iter().enumerate() {
+                signals[i].push(value);
+                match self.mode {
+                    PercentileMode::Exact => self.scratch[i].push(value),
+                    PercentileMode::P2 => estimators[i].push(value),
+                }
+            }
+        }
+
+        let [mag, x, y, z] = [0, 1, 2, 3].map(|i| {
+            // Percentiles of a window holding NaN are not defined, statrs' selection may even read out of bounds
+            let (percentile_25, percentile_75) = match self.mode {
+                _ if signals[i].has_nan => (f64::NAN, f64::NAN),
+                PercentileMode::Exact => (percentile(&mut self.scratch[i], 25), percentile(&mut self.scratch[i], 75)),
+                PercentileMode::P2 => (estimators[i].lower.estimate(), estimators[i].upper.estimate()),
+            };
+            signals[i].axis_stats(percentile_25, percentile_75)
+        });
+
+        Features::from_axes(mag, x, y, z)
+    }
+}
+
+// Mean, variance, min and max of one signal, in one pass
+struct SignalStats {
+    count: f64,
+    mean: f64,
+    sum: f64,
+    variance_sum: f64,
+    min: f64,
+    max: f64,
+    has_nan: bool,
+}
+
+impl SignalStats {
+    fn new() -> SignalStats {
+        SignalStats {
+            count: 0.0,
+            mean: 0.0,
+            sum: 0.0,
+            variance_sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            has_nan: false,
+        }
+    }
+
+    fn push(&mut self, value: f64) {
+        self.count += 1.0;
+
+        // Welford's update of the mean, the one used by statrs
+        self.mean += (value - self.mean) / self.count;
+
+        // statrs' single-pass variance (a sum-based form of Welford's update), kept as is for bit-compatibility
+        if self.count == 1.0 {
+            self.sum = value;
+        } else {
+            self.sum += value;
+            let diff = self.count * value - self.sum;
+            self.variance_sum += diff * diff / (self.count * (self.count - 1.0));
+        }
+
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.has_nan |= value.is_nan();
+    }
+
+    fn axis_stats(&self, percentile_25: f64, percentile_75: f64) -> AxisStats {
+        let mean = if self.count > 0.0 { self.mean } else { f64::NAN };
+        let variance = if self.count > 1.0 { self.variance_sum / (self.count - 1.0) } else { f64::NAN };
+        let std_dev = libm::sqrt(variance);
+        let coeff_var = if mean != 0.0 { std_dev / mean } else { 0.0 };
+
+        // No statistic is defined for an empty window, not even its bounds
+        let (min, max) = if self.count > 0.0 { (self.min, self.max) } else { (f64::NAN, f64::NAN) };
+
+        AxisStats {
+            mean,
+            std_dev,
+            min,
+            max,
+            coeff_var,
+            percentile_25,
+            percentile_75,
+        }
+    }
+}
+
+// statrs' percentile: R-8 quantile interpolation between two order statistics
+fn percentile(data: &mut [f64], p: usize) -> f64 {
+    let tau = p as f64 / 100.0;
+    if data.is_empty() {
+        return f64::NAN;
+    }
+
+    let h = (data.len() as f64 + 1.0 / 3.0) * tau + 1.0 / 3.0;
+    let hf = h as i64;
+
+    if hf <= 0 || tau == 0.0 {
+        return ordered_min(data);
+    }
+    if hf >= data.len() as i64 || tau == 1.0 {
+        return ordered_max(data);
+    }
+
+    let a = select(data, (hf as usize).saturating_sub(1));
+    let b = select(data, hf as usize);
+    a + (h - hf as f64) * (b - a)
+}
+
+fn ordered_min(data: &[f64]) -> f64 {
+    data[1..].iter().fold(data[0], |acc, &x| if x < acc || x.is_nan() { x } else { acc })
+}
+
+fn ordered_max(data: &[f64]) -> f64 {
+    data[1..].iter().fold(data[0], |acc, &x| if x > acc || x.is_nan() { x } else { acc })
+}
+
+// statrs' in-place quickselect, with bounds-checked indexing. Only called on NaN-free data
+fn select(arr: &mut [f64], rank: usize) -> f64 {
+    if rank == 0 {
+        return ordered_min(arr);
+    }
+    if rank > arr.len() - 1 {
+        return ordered_max(arr);
+    }
+
+    let mut low = 0;
+    let mut high = arr.len() - 1;
+    loop {
+        if high <= low + 1 {
+            if high == low + 1 && arr[high] < arr[low] {
+                arr.swap(low, high)
+            }
+            return arr[rank];
+        }
+
+        let middle = (low + high) >> 1;
+        arr.swap(middle, low + 1);
+
+        if arr[low] > arr[high] {
+            arr.swap(low, high);
+        }
+        if arr[low + 1] > arr[high] {
+            arr.swap(low + 1, high);
+        }
+        if arr[low] > arr[low + 1] {
+            arr.swap(low, low + 1);
+        }
+
+        let mut begin = low + 1;
+        let mut end = high;
+        let pivot = arr[begin];
+        loop {
+            loop {
+                begin += 1;
+                if arr[begin] >= pivot {
+                    break;
+                }
+            }
+            loop {
+                end -= 1;
+                if arr[end] <= pivot {
+                    break;
+                }
+            }
+            if end < begin {
+                break;
+            }
+            arr.swap(begin, end);
+        }
+
+        arr[low + 1] = arr[end];
+        arr[end] = pivot;
+
+        if end >= rank {
+            high = end - 1;
+        }
+        if end <= rank {
+            low = begin;
+        }
+    }
+}
+
+struct Quartiles {
+    lower: P2Estimator,
+    upper: P2Estimator,
+}
+
+impl Quartiles {
+    fn new() -> Quartiles {
+        Quartiles {
+            lower: P2Estimator::new(0.25),
+            upper: P2Estimator::new(0.75),
+        }
+    }
+
+    fn push(&mut self, value: f64) {
+        self.lower.push(value);
+        self.upper.push(value);
+    }
+}
+
+// P² quantile estimator: five markers whose heights are adjusted with a piecewise-parabolic
+// interpolation as samples arrive. Exact (statrs' interpolation) until five samples have been seen
+struct P2Estimator {
+    p: f64,
+    count: usize,
+    heights: [f64; 5],
+    positions: [f64; 5],
+    desired: [f64; 5],
+    increments: [f64; 5],
+}
+
+impl P2Estimator {
+    fn new(p: f64) -> P2Estimator {
+        P2Estimator {
+            p,
+            count: 0,
+            heights: [0.0; 5],
+            positions: [1.0, 2.0, 3.0, 4.0, 5.0],
+            desired: [1.0, 1.0 + 2.0 * p, 1.0 + 4.0 * p, 3.0 + 2.0 * p, 5.0],
+            increments: [0.0, p / 2.0, p, (1.0 + p) / 2.0, 1.0],
+        }
+    }
+
+    fn push(&mut self, value: f64) {
+        if self.count < 5 {
+            self.heights[self.count] = value;
+            self.count += 1;
+            if self.count == 5 {
+                self.heights.sort_unstable_by(|a, b| a.total_cmp(b));
+            }
+            return;
+        }
+        self.count += 1;
+
+        // Cell of the new sample, extending the extreme markers if needed
+        let cell = if value < self.heights[0] {
+            self.heights[0] = value;
+            0
+        } else if value >= self.heights[4] {
+            self.heights[4] = value;
+            3
+        } else {
+            (0..4).find(|&i| value < self.heights[i + 1]).unwrap_or(3)
+        };
+
+        for position in self.positions.iter_mut().skip(cell + 1) {
+            *position += 1.0;
+        }
+        for (desired, increment) in self.desired.iter_mut().zip(self.increments.iter()) {
+            *desired += increment;
+        }
+
+        for i in 1..4 {
+            let offset = self.desired[i] - self.positions[i];
+            if (offset >= 1.0 && self.positions[i + 1] - self.positions[i] > 1.0)
+                || (offset <= -1.0 && self.positions[i - 1] - self.positions[i] < -1.0)
+            {
+                let step = if offset >= 0.0 { 1.0 } else { -1.0 };
+                let parabolic = self.parabolic(i, step);
+                self.heights[i] = if self.heights[i - 1] < parabolic && parabolic < self.heights[i + 1] {
+                    parabolic
+                } else {
+                    self.linear(i, step)
+                };
+                self.positions[i] += step;
+            }
+        }
+    }
+
+    fn parabolic(&self, i: usize, step: f64) -> f64 {
+        let (q, n) = (&self.heights, &self.positions);
+        q[i] + step / (n[i + 1] - n[i - 1])
+            * ((n[i] - n[i - 1] + step) * (q[i + 1] - q[i]) / (n[i + 1] - n[i])
+                + (n[i + 1] - n[i] - step) * (q[i] - q[i - 1]) / (n[i] - n[i - 1]))
+    }
+
+    fn linear(&self, i: usize, step: f64) -> f64 {
+        let neighbour = if step > 0.0 { i + 1 } else { i - 1 };
+        self.heights[i] + step * (self.heights[neighbour] - self.heights[i]) / (self.positions[neighbour] - self.positions[i])
+    }
+
+    fn estimate(&self) -> f64 {
+        if self.count >= 5 {
+            return self.heights[2];
+        }
+
+        let mut first = self.heights;
+        percentile(&mut first[..self.count], (self.p * 100.0) as usize)
+    }
+}
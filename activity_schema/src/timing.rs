@@ -0,0 +1,44 @@
+use alloc::format;
+use alloc::string::String;
+use core::fmt::Display;
+
+// Time stamps of the payloads along the workflow, taken by every stage when it receives a payload and
+// when it sends the resulting one (only with hop_timing=true, see activity_pipeline::hops):
+// - in band, in the HopTimestamps of the payload, from the wall clock of the node. wasm32-unknown-unknown,
+//   the target the functions are deployed to, has no clock: no in-band stamps are then added.
+// - as hop marks cast to the "hop_log" output, mapped to a file-log resource with add-timestamp. The
+//   resource stamps every line with the clock of the node hosting it, which works on any target and
+//   stamps all the stages with one clock. The marks of a batch give its per-hop breakdown, each stamp
+//   being delayed by the cast from the stage to the resource
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HopEvent {
+    Received,
+    Sent,
+}
+
+// Wall clock of the node in microseconds, None where the target has no clock
+#[cfg(feature = "std")]
+pub fn now_us() -> Option<u64> {
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()
+            .map(|elapsed| elapsed.as_micros() as u64)
+    }
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    {
+        None
+    }
+}
+
+// Line cast to "hop_log": "<id> <stage> received|sent", the id being that of the batch up to
+// extract_features, which receives a batch and sends its windows, and that of the window after it
+pub fn hop_mark(id: impl Display, stage: &str, event: HopEvent) -> String {
+    let event = match event {
+        HopEvent::Received => "received",
+        HopEvent::Sent => "sent",
+    };
+    format!("{} {} {}", id, stage, event)
+}
@@ -0,0 +1,144 @@
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{AccelerometerData, ClassificationPayload, FeaturesPayload, SamplesPayload};
+
+// Encoding of the payloads exchanged along the workflow. It is selected with "encoding" in the
+// "init-payload" of generate_samples, detected from the header byte by the downstream functions
+// and kept for the payloads they forward. JSON has no header, it is recognized by its opening brace
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Encoding {
+    Json,
+    Cbor,
+    Bincode,
+    PackedF32,          // Samples as raw little-endian f32, the rest of the payload in bincode
+}
+
+const CBOR_HEADER: u8 = 0x01;
+const BINCODE_HEADER: u8 = 0x02;
+const PACKED_F32_HEADER: u8 = 0x03;
+const CONTROL_HEADER: u8 = 0x04;        // Followed by the name of the control message, in UTF-8
+
+// Payloads carrying a block of samples, written as raw f32 by the packed encoding.
+// The default implementation carries no samples, i.e. packed falls back to bincode
+pub trait PackedSamples {
+    fn take_samples(&mut self) -> Vec<f64> {
+        Vec::new()
+    }
+
+    fn put_samples(&mut self, _samples: Vec<f64>) {}
+}
+
+impl PackedSamples for SamplesPayload {
+    fn take_samples(&mut self) -> Vec<f64> {
+        std::mem::take(&mut self.batch)
+            .into_iter()
+            .flat_map(|sample| [sample.x, sample.y, sample.z])
+            .collect()
+    }
+
+    fn put_samples(&mut self, samples: Vec<f64>) {
+        self.batch = samples
+            .chunks_exact(3)
+            .map(|sample| AccelerometerData { x: sample[0], y: sample[1], z: sample[2] })
+            .collect();
+    }
+}
+
+impl PackedSamples for FeaturesPayload {}
+
+impl PackedSamples for ClassificationPayload {}
+
+impl Encoding {
+    pub fn from_name(name: &str) -> Option<Encoding> {
+        match name {
+            "json" => Some(Encoding::Json),
+            "cbor" => Some(Encoding::Cbor),
+            "bincode" => Some(Encoding::Bincode),
+            "packed_f32" => Some(Encoding::PackedF32),
+            _ => None,
+        }
+    }
+
+    fn detect(message: &[u8]) -> Result<Encoding, String> {
+        match message.first() {
+            Some(b'{') => Ok(Encoding::Json),
+            Some(&CBOR_HEADER) => Ok(Encoding::Cbor),
+            Some(&BINCODE_HEADER) => Ok(Encoding::Bincode),
+            Some(&PACKED_F32_HEADER) => Ok(Encoding::PackedF32),
+            Some(&CONTROL_HEADER) => Err("control message, not a payload".to_string()),
+            Some(header) => Err(format!("unknown header byte 0x{:02x}", header)),
+            None => Err("empty message".to_string()),
+        }
+    }
+}
+
+pub fn encode<T: Serialize + PackedSamples>(encoding: Encoding, mut payload: T) -> Result<Vec<u8>, String> {
+    match encoding {
+        Encoding::Json => serde_json::to_vec(&payload).map_err(|e| e.to_string()),
+        Encoding::Cbor => {
+            let mut message = vec![CBOR_HEADER];
+            ciborium::into_writer(&payload, &mut message).map_err(|e| e.to_string())?;
+            Ok(message)
+        }
+        Encoding::Bincode => {
+            let mut message = vec![BINCODE_HEADER];
+            bincode::serialize_into(&mut message, &payload).map_err(|e| e.to_string())?;
+            Ok(message)
+        }
+        Encoding::PackedF32 => {
+            // Header, number of values (u32), values, bincode of the payload without its samples
+            let samples = payload.take_samples();
+            let mut message = Vec::with_capacity(5 + 4 * samples.len());
+            message.push(PACKED_F32_HEADER);
+            message.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+            for value in samples {
+                message.extend_from_slice(&(value as f32).to_le_bytes());
+            }
+            bincode::serialize_into(&mut message, &payload).map_err(|e| e.to_string())?;
+            Ok(message)
+        }
+    }
+}
+
+pub fn decode<T: DeserializeOwned + PackedSamples>(message: &[u8]) -> Result<(Encoding, T), String> {
+    let encoding = Encoding::detect(message)?;
+
+    let payload = match encoding {
+        Encoding::Json => serde_json::from_slice(message).map_err(|e| e.to_string())?,
+        Encoding::Cbor => ciborium::from_reader(&message[1..]).map_err(|e| e.to_string())?,
+        Encoding::Bincode => bincode::deserialize(&message[1..]).map_err(|e| e.to_string())?,
+        Encoding::PackedF32 => {
+            let count_bytes = message.get(1..5).ok_or("truncated packed message")?;
+            let count = u32::from_le_bytes(count_bytes.try_into().unwrap()) as usize;
+            let values_end = count.checked_mul(4).and_then(|len| len.checked_add(5)).ok_or("truncated packed message")?;
+            let values = message.get(5..values_end).ok_or("truncated packed message")?;
+
+            let mut payload: T = bincode::deserialize(&message[values_end..]).map_err(|e| e.to_string())?;
+            payload.put_samples(
+                values
+                    .chunks_exact(4)
+                    .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()) as f64)
+                    .collect(),
+            );
+            payload
+        }
+    };
+
+    Ok((encoding, payload))
+}
+
+// Control messages a function casts to itself or to another one (e.g. the model checks of classify),
+// told apart from payloads by their header byte whatever the encoding of the payloads
+pub fn encode_control(name: &str) -> Vec<u8> {
+    let mut message = vec![CONTROL_HEADER];
+    message.extend_from_slice(name.as_bytes());
+    message
+}
+
+// Name of a control message, None for a payload
+pub fn decode_control(message: &[u8]) -> Option<&str> {
+    match message.split_first() {
+        Some((&CONTROL_HEADER, name)) => core::str::from_utf8(name).ok(),
+        _ => None,
+    }
+}
@@ -0,0 +1,19 @@
+// On-device feature extraction (extract_features) against the golden feature vectors
+use activity_schema::golden::golden_windows;
+use activity_schema::streaming::{FeatureExtractor, PercentileMode};
+
+#[test]
+fn exact_extraction_matches_golden_vectors() {
+    // A single extractor, as in extract_features, so that buffers reused across windows are covered too
+    let mut extractor = FeatureExtractor::new(PercentileMode::Exact);
+
+    let failures: Vec<String> = golden_windows()
+        .iter()
+        .filter_map(|window| {
+            let features = extractor.extract(&window.samples);
+            window.check(&activity_schema::feature_vector(&features, None, None)).err()
+        })
+        .collect();
+
+    assert!(failures.is_empty(), "Features diverge from the golden vectors:\n{}", failures.join("\n"));
+}
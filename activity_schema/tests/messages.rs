@@ -0,0 +1,62 @@
+// Payloads exchanged along the workflow, in the default JSON encoding
+use activity_schema::extended::extended_features;
+use activity_schema::streaming::{FeatureExtractor, PercentileMode};
+use activity_schema::{AccelerometerData, Classification, FeaturesPayload, SamplesPayload, WindowId};
+
+// Features of an empty window are all NaN, and a faulty one may give infinite ones: serde_json writes
+// both as null, which must be read back as NaN rather than fail, so that the batch is still classified
+#[test]
+fn non_finite_features_survive_json() {
+    let mut features = FeatureExtractor::new(PercentileMode::Exact).extract(&[]);
+    features.mean_x = f64::INFINITY;
+    features.min_y = 1.5;
+    let payload = FeaturesPayload {
+        batch_id: 7,
+        window_index: 0,
+        device_id: 1,
+        true_class: Classification::Walking,
+        features,
+        extended_features: Some(extended_features(&[], 20.0)),
+        spectral_features: None,
+        schema_hash: 42,
+        hops: Vec::new(),
+    };
+
+    let json = serde_json::to_string(&payload).unwrap();
+    let restored: FeaturesPayload = serde_json::from_str(&json).unwrap_or_else(|e| panic!("{} not read back: {}", json, e));
+
+    let values = activity_schema::feature_vector(&restored.features, restored.extended_features.as_ref(), None);
+    let expected = activity_schema::feature_vector(&payload.features, payload.extended_features.as_ref(), None);
+    assert_eq!(values.len(), expected.len());
+    for (value, expected) in values.iter().zip(expected) {
+        assert!(*value == expected || (value.is_nan() && !expected.is_finite()), "{} read back as {}", expected, value);
+    }
+    assert_eq!(restored.features.min_y, 1.5);
+    assert!(restored.features.mean_x.is_nan());
+}
+
+// Samples dropped by fault injection leave gaps in the sequence numbers of the batch
+#[test]
+fn sequences_skip_dropped_samples() {
+    let sample = AccelerometerData { x: 0.0, y: 0.0, z: 9.8 };
+    let payload = SamplesPayload {
+        batch_id: 3,
+        device_id: 0,
+        true_class: Classification::Sitting,
+        batch: vec![sample; 4],
+        overlap: 2,
+        first_sequence: Some(50),
+        dropped: vec![0, 3],
+        faults: vec!["dropped_samples".to_string()],
+        hops: Vec::new(),
+    };
+    assert_eq!(payload.sequences(), Some(vec![51, 52, 54, 55]));
+    assert_eq!(SamplesPayload { first_sequence: None, ..payload }.sequences(), None);
+}
+
+#[test]
+fn window_ids_are_unique_within_a_batch() {
+    // The first window is written as its batch, so that its TTC end line matches the start line
+    assert_eq!(WindowId { batch_id: 12, window_index: 0 }.to_string(), "12");
+    assert_eq!(WindowId { batch_id: 12, window_index: 1 }.to_string(), "12.1");
+}
@@ -0,0 +1,65 @@
+// Model families: what classifier_training stores must be read back by classify as the same model
+use activity_schema::models::{self, ModelKind};
+use smartcore::linalg::basic::matrix::DenseMatrix;
+
+// One cluster per class, around a different axis each, so that every family separates them
+fn clusters() -> (DenseMatrix<f64>, Vec<i32>) {
+    let mut rows = Vec::new();
+    let mut labels = Vec::new();
+    for class in 0..5i32 {
+        for sample in 0..40 {
+            let offset = (sample % 7) as f64 * 0.1 - 0.3;
+            rows.push((0..5).map(|axis| if axis == class { 3.0 + offset } else { offset / 2.0 }).collect::<Vec<f64>>());
+            labels.push(class);
+        }
+    }
+    let x = DenseMatrix::from_2d_vec(&rows);
+    (x, labels)
+}
+
+#[test]
+fn every_model_kind_round_trips() {
+    let (x, y) = clusters();
+
+    for kind in ModelKind::ALL {
+        assert_eq!(ModelKind::from_name(kind.name()), Some(kind));
+
+        let model = models::train(kind, &x, &y).unwrap_or_else(|e| panic!("Training {} failed: {}", kind.name(), e));
+        let serialized = model.serialize().unwrap();
+        let restored = models::deserialize(kind, &serialized).unwrap();
+
+        assert_eq!(restored.kind(), kind);
+        let predictions = restored.predict(&x).unwrap();
+        assert_eq!(predictions, model.predict(&x).unwrap(), "{} predicts differently once restored", kind.name());
+        assert_eq!(predictions, y, "{} does not separate the clusters", kind.name());
+    }
+}
+
+// Forests are read back with their trees exposed to count the votes: they must predict as smartcore does,
+// including on samples between the clusters, where the trees disagree
+#[test]
+fn forest_votes_match_smartcore() {
+    use smartcore::ensemble::random_forest_classifier::RandomForestClassifier;
+
+    let (x, y) = clusters();
+    let forest: RandomForestClassifier<f64, i32, DenseMatrix<f64>, Vec<i32>> =
+        RandomForestClassifier::fit(&x, &y, Default::default()).unwrap();
+    let restored = models::deserialize(ModelKind::RandomForest, &bincode::serialize(&forest).unwrap()).unwrap();
+
+    let mut rows = Vec::new();
+    for first in 0..5 {
+        for second in 0..5 {
+            rows.push((0..5).map(|axis| if axis == first || axis == second { 1.5 } else { 0.0 }).collect::<Vec<f64>>());
+        }
+    }
+    let samples = DenseMatrix::from_2d_vec(&rows);
+
+    let predictions = restored.predict(&samples).unwrap();
+    assert_eq!(predictions, forest.predict(&samples).unwrap());
+
+    for ((label, votes), prediction) in restored.predict_votes(&samples).unwrap().iter().zip(predictions) {
+        assert_eq!(*label, prediction);
+        assert!((votes.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(votes.iter().all(|&fraction| fraction <= votes[prediction as usize]));
+    }
+}
@@ -0,0 +1,20 @@
+// Spectral statistics: an analyzer reused across signals and window lengths must give the statistics
+// of a plan made for each signal
+use activity_schema::spectral::{spectral_stats, SpectralAnalyzer};
+
+#[test]
+fn reused_analyzer_matches_a_fresh_plan() {
+    let mut analyzer = SpectralAnalyzer::new();
+    for (n, frequency_hz) in [(100, 2.0), (100, 5.0), (64, 1.0), (100, 7.5), (1, 0.0)] {
+        let signal: Vec<f64> = (0..n)
+            .map(|i| 9.8 + (2.0 * std::f64::consts::PI * frequency_hz * i as f64 / 20.0).sin() + 0.1 * (i % 3) as f64)
+            .collect();
+
+        let reused = analyzer.stats(&signal, 20.0);
+        let fresh = spectral_stats(&signal, 20.0);
+        assert_eq!(format!("{:?}", reused), format!("{:?}", fresh), "Window of {} samples at {} Hz", n, frequency_hz);
+        if n > 1 {
+            assert!((reused.dominant_freq - frequency_hz).abs() <= 20.0 / n as f64);
+        }
+    }
+}
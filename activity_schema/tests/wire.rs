@@ -0,0 +1,110 @@
+// Payload encodings: whatever a function encodes must be decoded as the same payload by the next one
+use activity_schema::streaming::{FeatureExtractor, PercentileMode};
+use activity_schema::wire::{self, Encoding};
+use activity_schema::{AccelerometerData, Classification, ClassificationPayload, FeaturesPayload, HopTimestamps, SamplesPayload};
+
+const ENCODINGS: [Encoding; 4] = [Encoding::Json, Encoding::Cbor, Encoding::Bincode, Encoding::PackedF32];
+
+fn hops() -> Vec<HopTimestamps> {
+    vec![HopTimestamps { stage: "generate_samples".to_string(), received_us: Some(10), sent_us: None }]
+}
+
+// Samples exactly representable as f32, which is what the packed encoding carries
+fn samples_payload() -> SamplesPayload {
+    SamplesPayload {
+        batch_id: 3,
+        device_id: 2,
+        true_class: Classification::Stairs,
+        batch: (0..50).map(|i| AccelerometerData { x: i as f64 * 0.5, y: -9.75, z: 0.125 * i as f64 }).collect(),
+        overlap: 10,
+        first_sequence: Some(90),
+        dropped: vec![4],
+        faults: vec!["drop".to_string()],
+        hops: hops(),
+    }
+}
+
+#[test]
+fn samples_round_trip() {
+    for encoding in ENCODINGS {
+        let message = wire::encode(encoding, samples_payload()).unwrap();
+        let (decoded_encoding, payload): (Encoding, SamplesPayload) = wire::decode(&message).unwrap();
+
+        let expected = samples_payload();
+        assert_eq!(decoded_encoding, encoding);
+        assert_eq!(payload.batch, expected.batch, "{:?}", encoding);
+        assert_eq!((payload.batch_id, payload.device_id, payload.true_class), (expected.batch_id, expected.device_id, expected.true_class));
+        assert_eq!((payload.overlap, &payload.faults, payload.hops.len()), (expected.overlap, &expected.faults, 1));
+        assert_eq!(payload.sequences(), expected.sequences());
+    }
+}
+
+#[test]
+fn features_round_trip() {
+    let samples = samples_payload().batch;
+    let features = FeatureExtractor::new(PercentileMode::Exact).extract(&samples);
+
+    for encoding in ENCODINGS {
+        let payload = FeaturesPayload {
+            batch_id: 3,
+            window_index: 1,
+            device_id: 2,
+            true_class: Classification::Stairs,
+            features: features.clone(),
+            extended_features: None,
+            spectral_features: None,
+            schema_hash: 0x1234,
+            hops: hops(),
+        };
+        let message = wire::encode(encoding, payload).unwrap();
+        let (decoded_encoding, payload): (Encoding, FeaturesPayload) = wire::decode(&message).unwrap();
+
+        assert_eq!(decoded_encoding, encoding);
+        assert_eq!(payload.features, features, "{:?}", encoding);
+        assert_eq!((payload.window_index, payload.schema_hash), (1, 0x1234));
+    }
+}
+
+#[test]
+fn classification_round_trip() {
+    for encoding in ENCODINGS {
+        let payload = ClassificationPayload {
+            batch_id: 3,
+            window_index: 1,
+            device_id: 2,
+            true_class: Classification::Stairs,
+            classification: Classification::Unknown,
+            model_version: Some(1700000000),
+            class_votes: Some([0.25, 0.25, 0.0, 0.3, 0.2]),
+            hops: hops(),
+        };
+        let message = wire::encode(encoding, payload).unwrap();
+        let (decoded_encoding, payload): (Encoding, ClassificationPayload) = wire::decode(&message).unwrap();
+
+        assert_eq!(decoded_encoding, encoding);
+        assert_eq!(payload.classification, Classification::Unknown, "{:?}", encoding);
+        assert_eq!((payload.window_index, payload.model_version), (1, Some(1700000000)));
+        assert_eq!(payload.class_votes, Some([0.25, 0.25, 0.0, 0.3, 0.2]));
+    }
+}
+
+#[test]
+fn invalid_messages_are_errors() {
+    assert!(wire::decode::<SamplesPayload>(&[]).is_err());
+    assert!(wire::decode::<SamplesPayload>(&[0x7f, 1, 2]).is_err());
+    assert!(wire::decode::<SamplesPayload>(&[0x03, 0xff, 0xff, 0xff, 0xff]).is_err());          // Truncated packed samples
+}
+
+// Whatever its bytes, a payload is never taken for a control message, nor a control message for a payload
+#[test]
+fn control_messages_are_not_payloads() {
+    let control = wire::encode_control("reload_model");
+    assert_eq!(wire::decode_control(&control), Some("reload_model"));
+    assert!(wire::decode::<SamplesPayload>(&control).is_err());
+
+    for encoding in ENCODINGS {
+        let message = wire::encode(encoding, samples_payload()).unwrap();
+        assert_eq!(wire::decode_control(&message), None, "{:?}", encoding);
+    }
+    assert_eq!(wire::decode_control(b"reload_model"), None);
+}
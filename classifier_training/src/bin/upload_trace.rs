@@ -0,0 +1,63 @@
+use std::error::Error;
+use csv::Reader;
+use serde::{Serialize, Deserialize};
+use rusqlite::{params, Connection};
+
+// Same layout as the preprocessed dataset read by the trainer
+#[derive(Debug, Serialize, Deserialize)]
+struct DataRow {
+    activity: String,
+    accel_x: f64,
+    accel_y: f64,
+    accel_z: f64,
+}
+
+// Metadata of a single WorkflowState row, as read back by generate_samples in replay mode
+#[derive(Serialize)]
+struct TraceChunk<'a> {
+    rows: &'a [DataRow],
+}
+
+// Stores a recorded trace into the EDGELESS SQLite DB, split into rows with id "<trace_id>_<chunk_index>"
+// Usage: upload_trace <trace.csv> <path-to-SQLite-EDGELESS-db> <trace_id> [chunk_size]
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 4 {
+        println!("Usage: {} <trace.csv> <path-to-SQLite-EDGELESS-db> <trace_id> [chunk_size]", args[0]);
+        return Ok(());
+    }
+
+    let input_file = &args[1];
+    let db_path = &args[2];
+    let trace_id = &args[3];
+    let chunk_size = match args.get(4) {
+        Some(size) => size.parse::<usize>()?,
+        None => 500,
+    };
+
+    let mut rdr = Reader::from_path(input_file)?;
+    let mut rows = Vec::new();
+    for result in rdr.deserialize() {
+        let record: DataRow = result?;
+        rows.push(record);
+    }
+
+    println!("[INFO] Read {} samples from '{}'", rows.len(), input_file);
+
+    let sqlite_conn = Connection::open(db_path)?;
+    let mut num_chunks = 0;
+
+    for (chunk_index, chunk) in rows.chunks(chunk_size).enumerate() {
+        let metadata_json = serde_json::to_string(&TraceChunk { rows: chunk })?;
+
+        sqlite_conn.execute(
+            "INSERT OR REPLACE INTO WorkflowState (id, metadata) VALUES (?1, ?2)",
+            params![format!("{}_{}", trace_id, chunk_index), metadata_json],
+        )?;
+        num_chunks += 1;
+    }
+
+    println!("[INFO] Trace '{}' saved to SQLite DB in {} chunks of up to {} samples", trace_id, num_chunks, chunk_size);
+
+    Ok(())
+}
@@ -4,22 +4,36 @@ use csv::Reader;
 use statrs::statistics::{OrderStatistics, Statistics};
 use std::f64;
 use smartcore::linalg::basic::matrix::DenseMatrix;
-use smartcore::ensemble::random_forest_classifier::RandomForestClassifier;
 use std::time::Instant;
 use serde::{Serialize, Deserialize};
-use bincode;
 use rusqlite::{params, Connection, Result};
+use base64::Engine;
+use activity_schema::{AccelerometerData, AxisStats, Classification, FeatureGroups, SpectralFeatures};
+use activity_schema::gravity::{GravityFilter, GRAVITY_CUTOFF_HZ};
+use activity_schema::models::{self, ModelKind};
 
 #[derive(Serialize, Deserialize)]
 struct ModelMetadata {
+    model_id: String,                       // Id classify selects the model with, the row being keyed by id and version
     classifier_base64: String,              // Serialized model as Base64 (OK for JSON in SQLite table)
+    model_kind: ModelKind,                  // Family of the model, to deserialize it into
+    schema_hash: u64,                       // Feature schema the model is trained on, checked by classify
+    model_version: u64,                     // Training time in seconds since the epoch, classify swaps models when it changes
 }
 
-#[derive(Serialize, Deserialize)]
-struct SerializableModel {
-    classifier: RandomForestClassifier<f64, i32, DenseMatrix<f64>, Vec<i32>>,
+// Keeps the models trained before under the same id, so that classify can still pin any of their versions
+fn save_model(sqlite_conn: &Connection, metadata: &ModelMetadata) -> Result<String, Box<dyn Error>> {
+    let row_id = format!("{}@{}", metadata.model_id, metadata.model_version);
+    sqlite_conn.execute(
+        "INSERT OR REPLACE INTO WorkflowState (id, metadata) VALUES (?1, ?2)",
+        params![row_id, serde_json::to_string(metadata)?],
+    )?;
+    Ok(row_id)
 }
 
+// Sampling rate of the dataset, used by the jerk and the spectral features
+const SAMPLE_RATE_HZ: f64 = 20.0;
+
 // Structure to hold a single row of data
 #[derive(Debug, Deserialize, Clone)]
 struct DataRow {
@@ -29,9 +43,9 @@ struct DataRow {
     accel_z: f64,
 }
 
-// Structure for extracted features
+// Structure for extracted features, in the column order of activity_schema::feature_vector()
 #[derive(Debug, Clone)]
-struct Features([f64; 28]);
+struct Features(Vec<f64>);
 
 // Read CSV file and load data into a vector
 fn read_csv(file_path: &str) -> Result<Vec<DataRow>, Box<dyn Error>> {
@@ -71,9 +85,11 @@ fn print_min_max_per_activity(data: Vec<DataRow>) {
 }
 
 
-fn compute_stats(mut data: Vec<f64>) -> (f64, f64, f64, f64, f64, f64, f64) {
+fn compute_stats(mut data: Vec<f64>) -> AxisStats {
+    // No statistic is defined for an empty window, as in extract_features
     if data.is_empty() {
-        return (0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        let nan = f64::NAN;
+        return AxisStats { mean: nan, std_dev: nan, min: nan, max: nan, coeff_var: nan, percentile_25: nan, percentile_75: nan };
     }
 
     let mean = data.clone().mean();
@@ -85,14 +101,14 @@ fn compute_stats(mut data: Vec<f64>) -> (f64, f64, f64, f64, f64, f64, f64) {
         coeff_var = std_dev / mean;
     }
 
-    let p25 = data.percentile(25);
-    let p75 = data.percentile(75);
+    let percentile_25 = data.percentile(25);
+    let percentile_75 = data.percentile(75);
 
-    (mean, std_dev, min, max, coeff_var, p25, p75)
+    AxisStats { mean, std_dev, min, max, coeff_var, percentile_25, percentile_75 }
 }
 
 // Compute statistical features for a chunk
-fn compute_features(chunk: &[DataRow]) -> Features {
+fn compute_features(chunk: &[DataRow], groups: FeatureGroups) -> Features {
     let mut x_vals = Vec::with_capacity(chunk.len());
     let mut y_vals = Vec::with_capacity(chunk.len());
     let mut z_vals = Vec::with_capacity(chunk.len());
@@ -106,35 +122,82 @@ fn compute_features(chunk: &[DataRow]) -> Features {
         magnitudes.push(mag);
     }
 
-    let (mean_mag, std_dev_mag, min_mag, max_mag, coeff_var_mag, p25_mag, p75_mag) = compute_stats(magnitudes);
-    let (mean_x, std_dev_x, min_x, max_x, coeff_var_x, p25_x, p75_x) = compute_stats(x_vals);
-    let (mean_y, std_dev_y, min_y, max_y, coeff_var_y, p25_y, p75_y) = compute_stats(y_vals);
-    let (mean_z, std_dev_z, min_z, max_z, coeff_var_z, p25_z, p75_z) = compute_stats(z_vals);
+    // Same extended and spectral computations as extract_features
+    let extended_features = if groups.extended {
+        let samples: Vec<AccelerometerData> = chunk
+            .iter()
+            .map(|row| AccelerometerData { x: row.accel_x, y: row.accel_y, z: row.accel_z })
+            .collect();
+        Some(activity_schema::extended::extended_features(&samples, SAMPLE_RATE_HZ))
+    } else {
+        None
+    };
+    let spectral_features = if groups.spectral {
+        let stats = |signal: &[f64]| activity_schema::spectral::spectral_stats(signal, SAMPLE_RATE_HZ);
+        Some(SpectralFeatures::from_signals(stats(&magnitudes), stats(&x_vals), stats(&y_vals), stats(&z_vals)))
+    } else {
+        None
+    };
+
+    let features = activity_schema::Features::from_axes(
+        compute_stats(magnitudes),
+        compute_stats(x_vals),
+        compute_stats(y_vals),
+        compute_stats(z_vals),
+    );
+
+    Features(activity_schema::feature_vector(&features, extended_features.as_ref(), spectral_features.as_ref()))
+}
+
+// Sliding windows of window_size rows, starting every stride rows (same windowing as generate_samples).
+// Only complete windows are kept
+fn windows(rows: &[DataRow], window_size: usize, stride: usize) -> Vec<&[DataRow]> {
+    if rows.len() < window_size {
+        return Vec::new();
+    }
+
+    (0..=rows.len() - window_size)
+        .step_by(stride)
+        .map(|start| &rows[start..start + window_size])
+        .collect()
+}
 
-    Features([
-        mean_mag, std_dev_mag, min_mag, max_mag, coeff_var_mag, p25_mag, p75_mag,
-        mean_x, std_dev_x, min_x, max_x, coeff_var_x, p25_x, p75_x,
-        mean_y, std_dev_y, min_y, max_y, coeff_var_y, p25_y, p75_y,
-        mean_z, std_dev_z, min_z, max_z, coeff_var_z, p25_z, p75_z,
-    ])
+// Window size and stride given as the first two arguments, the stride being between 1 and the window size
+fn window_arguments(args: &[String]) -> Result<(usize, usize), Box<dyn Error>> {
+    let window_size = match args.get(1) {
+        Some(value) => value.parse::<usize>()?,
+        None => 100,
+    };
+    let stride = match args.get(2) {
+        Some(value) => value.parse::<usize>()?,
+        None => window_size,
+    };
+    if window_size == 0 || stride == 0 || stride > window_size {
+        return Err(format!("Invalid window size {} and stride {}: the stride must be between 1 and the window size", window_size, stride).into());
+    }
+    Ok((window_size, stride))
 }
 
-fn extract_features(data: Vec<DataRow>) -> HashMap<String, Vec<Features>> {
+fn extract_features(data: Vec<DataRow>, window_size: usize, stride: usize, groups: FeatureGroups) -> HashMap<String, Vec<Features>> {
     let mut activity_map: HashMap<String, Vec<DataRow>> = HashMap::new();
 
     // Group by activity
     for row in data {
-        activity_map.entry(row.activity.clone()).or_insert_with(Vec::new).push(row);
+        activity_map.entry(row.activity.clone()).or_default().push(row);
     }
 
     // Convert each activity's data into feature vectors
     let mut feature_map: HashMap<String, Vec<Features>> = HashMap::new();
     
-    for (activity, rows) in &activity_map {
-        let chunks: Vec<&[DataRow]> = rows.chunks(100).collect();
+    for (activity, rows) in activity_map.iter_mut() {
+        if groups.body {
+            remove_gravity(rows);
+        }
+
+        let chunks: Vec<&[DataRow]> = windows(rows, window_size, stride);
         
         // Compute features for each chunk
-        let feature_vectors: Vec<Features> = chunks.iter().map(|chunk| compute_features(chunk)).collect();
+        let feature_vectors: Vec<Features> = chunks.iter().map(|chunk| compute_features(chunk, groups)).collect();
         feature_map.insert(activity.clone(), feature_vectors.clone());
 
         println!("[INFO] Processed '{}' activity data, #feature vectors: {}", activity, feature_vectors.len());
@@ -144,12 +207,50 @@ fn extract_features(data: Vec<DataRow>) -> HashMap<String, Vec<Features>> {
 }
 
 
+// Replaces the samples with their body acceleration, filtering the rows of an activity as a continuous
+// stream, as extract_features does with the consecutive batches of a device
+fn remove_gravity(rows: &mut [DataRow]) {
+    let mut samples: Vec<AccelerometerData> = rows
+        .iter()
+        .map(|row| AccelerometerData { x: row.accel_x, y: row.accel_y, z: row.accel_z })
+        .collect();
+    GravityFilter::new(GRAVITY_CUTOFF_HZ, SAMPLE_RATE_HZ).separate(&mut samples, 0);
+
+    for (row, sample) in rows.iter_mut().zip(samples) {
+        row.accel_x = sample.x;
+        row.accel_y = sample.y;
+        row.accel_z = sample.z;
+    }
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
     let input_file = "preprocessed_dataset.csv";
 
-    println!("[INFO] Extracting features...");
+    // Optional arguments: window size and stride, in samples (default: non-overlapping windows of 100 samples),
+    // then the optional feature groups to train on, "extended" and/or "spectral", and "body" to compute the features
+    // on the body acceleration (to be enabled in extract_features as well), "model=<kind>" to train another model family
+    // than a random forest (see activity_schema::models) and "model_id=<id>" to store the model under another id
+    // than base64_model (selected with model_id in the init-payload of classify)
+    let args: Vec<String> = std::env::args().collect();
+    let (window_size, stride) = window_arguments(&args)?;
+    let option = |key: &str| args.iter().skip(3).find_map(|arg| arg.strip_prefix(key)?.strip_prefix('='));
+    let model_id = option("model_id").unwrap_or("base64_model");
+    let model_kind = match option("model") {
+        Some(name) => ModelKind::from_name(name).ok_or_else(|| {
+            let names: Vec<&str> = ModelKind::ALL.iter().map(|kind| kind.name()).collect();
+            format!("Unknown model, expected one of {}", names.join(", "))
+        })?,
+        None => ModelKind::RandomForest,
+    };
+    let groups = FeatureGroups::from_names(args.iter().skip(3).map(String::as_str).filter(|arg| !arg.contains('=')))
+        .ok_or("Unknown feature group, expected extended, spectral or body")?;
+
+    println!(
+        "[INFO] Extracting features (window size: {}, stride: {}, extended: {}, spectral: {}, body acceleration: {})...",
+        window_size, stride, groups.extended, groups.spectral, groups.body
+    );
     let data = read_csv(input_file)?;
-    let feature_map = extract_features(data.clone());
+    let feature_map = extract_features(data.clone(), window_size, stride, groups);
 
     print_min_max_per_activity(data.clone());
 
@@ -159,41 +260,43 @@ fn main() -> Result<(), Box<dyn Error>> {
     let mut labels: Vec<i32> = Vec::new();
 
     for (activity, features_vectors) in feature_map {
-        let label = match activity.as_str() {
-            "jogging" => 0,
-            "walking" => 1,
-            "standing" => 2,
-            "stairs" => 3,
-            "sitting" => 4,
-            _ => -1,
+        let label = match Classification::from_activity(&activity) {
+            Some(class) => class.label() as i32,
+            None => {
+                println!("[WARN] Skipping unknown activity '{}'", activity);
+                continue;
+            }
         };
 
         for feature_vector in features_vectors {
-            features_data.push(feature_vector.0.to_vec());
+            features_data.push(feature_vector.0);
             labels.push(label);
         }
     }
 
+    let num_features = features_data.first().map_or(0, |features| features.len());
     let x = DenseMatrix::from_2d_vec(&features_data);
 
     // Train the classifier
     println!("[INFO] Training...");
     let start = Instant::now();
-    let classifier = RandomForestClassifier::fit(&x, &labels, Default::default()).unwrap();
+    let classifier = models::train(model_kind, &x, &labels)?;
     let duration = start.elapsed();
-    println!("[INFO] Random Forest classifier trained in {:.2?}", duration);
+    println!("[INFO] {} classifier trained in {:.2?}", model_kind.name(), duration);
 
 
     println!("----------------------------------------------------------------");
     println!("[INFO] Testing:");
-    let data = vec![
+    let data = [
         vec![6.05, 2.15, 5.08, 90.005, 67.018, 12.009, 78.620, 56.08, 12.18, 45.09, 45.007, 45.017, 78.009, 0.620, 0.07, 11.16, 14.08, 33.006,44.016, 13.008, 0.620, 0.07, 783.15, 13.08, 0.006, 84.015, 13.008, 90.620],  // Low activity  
         vec![0.2, 0.35, 0.22, 0.03, 0.045, 0.025, 0.650, 0.22, 0.33, 0.21, 0.028, 0.043, 0.024, 0.650, 0.21, 0.31, 0.2, 0.026, 0.041, 0.022, 0.650, 0.2, 0.3, 0.19, 0.025, 0.04, 0.021, 0.650],  // Moderate activity  
         vec![0.4, 0.6, 0.45, 0.07, 0.09, 0.05, 0.670, 0.42, 0.58, 0.44, 0.065, 0.088, 0.048, 0.670, 0.41, 0.55, 0.42, 0.063, 0.085, 0.047, 0.670, 0.4, 0.53, 0.41, 0.06, 0.08, 0.045, 0.670],  // High activity  
     ];
 
     for (i, test_sample_vec) in data.iter().enumerate() {
-        let test_sample = DenseMatrix::from_2d_vec(&vec![test_sample_vec.clone()]);
+        let mut test_sample_vec = test_sample_vec.clone();
+        test_sample_vec.resize(num_features, 0.0);          // Extended and spectral features, if any, left at zero
+        let test_sample = DenseMatrix::from_2d_vec(&vec![test_sample_vec]);
         let prediction = classifier.predict(&test_sample).unwrap();
         println!("Test Sample {}: Predicted Class: {:?}", i + 1, prediction);
     }
@@ -201,28 +304,91 @@ fn main() -> Result<(), Box<dyn Error>> {
     println!("----------------------------------------------------------------");
     println!("[INFO] Serializing...");
 
-    let serializable_model = SerializableModel { classifier };
-    let serialized = bincode::serialize(&serializable_model).unwrap();
+    let serialized = classifier.serialize()?;
 
-    let encoded_model = base64::encode(&serialized);
+    let encoded_model = base64::engine::general_purpose::STANDARD.encode(&serialized);
 
     let metadata = ModelMetadata {
+        model_id: model_id.to_string(),
         classifier_base64: encoded_model,
+        model_kind,
+        schema_hash: activity_schema::schema_hash(groups),
+        model_version: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs(),
     };
 
-    let metadata_json = serde_json::to_string(&metadata).unwrap();
-    println!("[INFO] The model has been serialized");
+    println!("[INFO] The model has been serialized (version {})", metadata.model_version);
 
     println!("----------------------------------------------------------------");
     println!("[INFO] Saving into SQLite DB...");
 
     let sqlite_conn = Connection::open("<path-to-SQLite-EDGELESS-db>")?;
-    let _ = sqlite_conn.execute(
-        "INSERT INTO WorkflowState (id, metadata) VALUES (?1, ?2)",
-        params!["base64_model", metadata_json],
-    );
+    // Latest version of the id, which a running classify picks up at its next model check unless it pins another one
+    let row_id = save_model(&sqlite_conn, &metadata)?;
 
-    println!("[INFO] Model saved to SQLite DB");
+    println!("[INFO] Model saved to SQLite DB with id {} (row {})", model_id, row_id);
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use activity_schema::golden::golden_windows;
+
+    // The features the model is trained on must be those computed on the device
+    #[test]
+    fn compute_features_matches_golden_vectors() {
+        let failures: Vec<String> = golden_windows()
+            .iter()
+            .filter_map(|window| {
+                let rows: Vec<DataRow> = window
+                    .samples
+                    .iter()
+                    .map(|sample| DataRow { activity: String::new(), accel_x: sample.x, accel_y: sample.y, accel_z: sample.z })
+                    .collect();
+                window.check(&compute_features(&rows, FeatureGroups::default()).0).err()
+            })
+            .collect();
+
+        assert!(failures.is_empty(), "Training features diverge from the golden vectors:\n{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn window_arguments_are_validated() {
+        let args = |values: &[&str]| -> Vec<String> { std::iter::once("classifier_training").chain(values.iter().copied()).map(String::from).collect() };
+
+        assert_eq!(window_arguments(&args(&[])).unwrap(), (100, 100));
+        assert_eq!(window_arguments(&args(&["200"])).unwrap(), (200, 200));
+        assert_eq!(window_arguments(&args(&["100", "50", "spectral"])).unwrap(), (100, 50));
+        for invalid in [&["100", "0"][..], &["0"], &["100", "101"], &["-1"]] {
+            assert!(window_arguments(&args(invalid)).is_err(), "{:?} accepted", invalid);
+        }
+    }
+
+    // A retrain adds a row next to the previous versions instead of replacing them
+    #[test]
+    fn save_model_keeps_previous_versions() {
+        let sqlite_conn = Connection::open_in_memory().unwrap();
+        sqlite_conn.execute("CREATE TABLE WorkflowState (id TEXT PRIMARY KEY, metadata TEXT)", []).unwrap();
+
+        for model_version in [100, 200] {
+            let metadata = ModelMetadata {
+                model_id: "base64_model".to_string(),
+                classifier_base64: String::new(),
+                model_kind: ModelKind::RandomForest,
+                schema_hash: 0,
+                model_version,
+            };
+            save_model(&sqlite_conn, &metadata).unwrap();
+        }
+
+        let versions: Vec<u64> = sqlite_conn
+            .prepare("SELECT json_extract(metadata, '$.model_version') FROM WorkflowState WHERE json_extract(metadata, '$.model_id') = 'base64_model' ORDER BY json_extract(metadata, '$.model_version') DESC")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(versions, vec![200, 100]);
+    }
+}
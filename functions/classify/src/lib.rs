@@ -1,198 +1,75 @@
 use edgeless_function::*;
-use smartcore::linalg::basic::matrix::DenseMatrix;
-use smartcore::ensemble::random_forest_classifier::RandomForestClassifier;
 use std::sync::{Mutex, OnceLock};
-use serde::{Serialize, Deserialize};
-use bincode;
+use activity_schema::timing::HopEvent;
+use activity_schema::wire;
+use activity_schema::{ClassificationPayload, FeaturesPayload};
+use activity_pipeline::{hops, model};
 
 struct ClassifyFun;
 
-struct State {
-    classifier: RandomForestClassifier<f64, i32, DenseMatrix<f64>, Vec<i32>>,
+struct InitState {                  // Populated by what has been specified into "init-payload" in workflow.json
+    model: model::ModelSelector,                // Model to retrieve from the database
+    model_check_interval_ms: u64,               // Period of the checks for a new model version in the database, 0 to disable them
+    confidence_threshold: f64,                  // Fraction of the votes below which a prediction is rejected as Unknown, 0 to accept all of them
+    hop_timing: hops::HopTiming,                // Per-hop latency instrumentation, off by default
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Default)]
-struct SqlxClassifierData {
-    id: String,
-    metadata: ClassifierData,
-}
-
-// Metadata to hold the classifier as base64 string
-#[derive(serde::Serialize, serde::Deserialize, Default, Debug)]
-struct ClassifierData {
-    classifier_base64: String, // Serialized model as base64
-}
-
-// This will hold the actual classifier after decoding and deserializing
-#[derive(serde::Serialize, serde::Deserialize)]
-struct SerializableModel {
-    classifier: RandomForestClassifier<f64, i32, DenseMatrix<f64>, Vec<i32>>,
-}
+// Control messages (see activity_schema::wire::encode_control).
+// Sent by classify to itself every model_check_interval_ms
+const CHECK_MODEL_MESSAGE: &str = "check_model";
+// Can be cast to classify by any function mapped to it, to check for a new model version at once
+const RELOAD_MODEL_MESSAGE: &str = "reload_model";
+// Sent by classify to itself, with a growing delay, until the model could be retrieved
+const RETRY_MODEL_MESSAGE: &str = "retry_model";
 
-#[derive(Debug, Deserialize)]
-struct Features {
-    mean_mag: f64,
-    std_dev_mag: f64,
-    min_mag: f64,
-    max_mag: f64,
-    coeff_var_mag: f64,
-    percentile_25_mag: f64,
-    percentile_75_mag: f64,
-
-    mean_x: f64,
-    std_dev_x: f64,
-    min_x: f64,
-    max_x: f64,
-    coeff_var_x: f64,
-    percentile_25_x: f64,
-    percentile_75_x: f64,
-
-    mean_y: f64,
-    std_dev_y: f64,
-    min_y: f64,
-    max_y: f64,
-    coeff_var_y: f64,
-    percentile_25_y: f64,
-    percentile_75_y: f64,
-
-    mean_z: f64,
-    std_dev_z: f64,
-    min_z: f64,
-    max_z: f64,
-    coeff_var_z: f64,
-    percentile_25_z: f64,
-    percentile_75_z: f64,
-}
-
-#[derive(Debug, Deserialize)]
-struct ReceivedPayload {
-    batch_id: u64,
-    features: Features,
-}
-
-#[derive(Debug, Serialize)]
-enum Classification {
-    Jogging,
-    Walking,
-    Standing,
-    Stairs,
-    Sitting,
-}
-
-#[derive(Debug, Serialize)]
-struct ClassificationPayload {
-    batch_id: u64,
-    classification: Classification,
-}
-
-static STATE: OnceLock<Mutex<State>> = OnceLock::new();
-
-
-fn call_wrapper(msg: &str) -> Option<SqlxClassifierData> {
-    match call("sqlx_database", msg.as_bytes()) {
-        CallRet::Reply(msg) => {
-            let reply = std::str::from_utf8(&msg).unwrap_or("not UTF-8");
-            // log::info!("Response from DB: {:?}", reply);
-            let cur_state: SqlxClassifierData = serde_json::from_str(reply).unwrap_or_default();
-            Some(cur_state)
-        }
-        CallRet::NoReply => {
-            log::warn!("Received empty reply from the DB");
-            None
-        }
-        CallRet::Err => {
-            log::error!("Error while calling the DB");
-            None
-        }
-    }
-}
+static INIT_STATE: OnceLock<InitState> = OnceLock::new();
+static STATE: OnceLock<Mutex<Option<model::Model>>> = OnceLock::new();         // None until a model could be retrieved
+static LOADING: OnceLock<Mutex<model::Loading>> = OnceLock::new();           // Locked after STATE, when both are
 
 impl EdgeFunction for ClassifyFun {
 
     // ------ EDGELESS FUNCTIONS REDEFINITION ------
     fn handle_cast(_src: InstanceId, encoded_message: &[u8]) {
-        let state = STATE.get().expect("Classifier not initialized").lock().unwrap();
+        let received_us = INIT_STATE.get().unwrap().hop_timing.received_us();
 
-        let str_message = core::str::from_utf8(encoded_message).unwrap();
-        let received_data: ReceivedPayload = match serde_json::from_str(str_message) {
-            Ok(parsed_received_data) => parsed_received_data,
-            Err(err) => {
-                log::info!("Failed to deserialize message: {}", err);
+        // --------- Model retrieval and hot-reload ---------
+        match wire::decode_control(encoded_message) {
+            Some(RETRY_MODEL_MESSAGE) => {
+                retry_model();
                 return;
             }
-        };
-
-        let batch_id = received_data.batch_id;
-        let extracted_features = received_data.features;
-
-        // Features struct into Vec<f64> array
-        let features_vec: Vec<f64> = vec![
-            extracted_features.mean_mag,
-            extracted_features.std_dev_mag,
-            extracted_features.min_mag,
-            extracted_features.max_mag,
-            extracted_features.coeff_var_mag,
-            extracted_features.percentile_25_mag,
-            extracted_features.percentile_75_mag,
-
-            extracted_features.mean_x,
-            extracted_features.std_dev_x,
-            extracted_features.min_x,
-            extracted_features.max_x,
-            extracted_features.coeff_var_x,
-            extracted_features.percentile_25_x,
-            extracted_features.percentile_75_x,
-
-            extracted_features.mean_y,
-            extracted_features.std_dev_y,
-            extracted_features.min_y,
-            extracted_features.max_y,
-            extracted_features.coeff_var_y,
-            extracted_features.percentile_25_y,
-            extracted_features.percentile_75_y,
-
-            extracted_features.mean_z,
-            extracted_features.std_dev_z,
-            extracted_features.min_z,
-            extracted_features.max_z,
-            extracted_features.coeff_var_z,
-            extracted_features.percentile_25_z,
-            extracted_features.percentile_75_z,
-        ];
-
-        let sample = DenseMatrix::from_2d_vec(&vec![features_vec]);
-
-        let prediction = state.classifier.predict(&sample).unwrap();
-
-        let classification_result;
-        if prediction[0] == 0 {
-            classification_result = Classification::Jogging;
-        } else if prediction[0] == 1 {
-            classification_result = Classification::Walking;
-        } else if prediction[0] == 2 {
-            classification_result = Classification::Standing;
-        } else if prediction[0] == 3 {
-            classification_result = Classification::Stairs;
-        } else {
-            classification_result = Classification::Sitting;
+            Some(control @ (CHECK_MODEL_MESSAGE | RELOAD_MODEL_MESSAGE)) => {
+                reload_model();
+                let model_check_interval_ms = INIT_STATE.get().unwrap().model_check_interval_ms;
+                if control == CHECK_MODEL_MESSAGE && model_check_interval_ms > 0 {
+                    delayed_cast(model_check_interval_ms, "self", &wire::encode_control(CHECK_MODEL_MESSAGE));
+                }
+                return;
+            }
+            Some(control) => {
+                log::info!("Unknown control message: {}", control);
+                return;
+            }
+            None => {}
         }
 
-        log::info!("Classified the received features: {:?}", prediction);
-
-        let payload = ClassificationPayload {
-            batch_id,
-            classification: classification_result,
+        let Some((encoding, received_data)) = decode_features(encoded_message) else {
+            return;
         };
+        INIT_STATE.get().unwrap().hop_timing.mark(received_data.window_id(), "classify", HopEvent::Received);
 
-        let serialized_classification_result = match serde_json::to_string(&payload) {
-            Ok(json) => json,
-            Err(e) => {
-                log::info!("Error serializing classification result: {}", e);
-                String::new()
+        let state = STATE.get().unwrap().lock().unwrap();
+
+        // Kept until a retry retrieves the model
+        let model = match state.as_ref() {
+            Some(model) => model,
+            None => {
+                LOADING.get().unwrap().lock().unwrap().buffer(received_us, encoded_message);
+                return;
             }
         };
 
-        cast("classification_result", serialized_classification_result.as_bytes());
+        classify(model, received_us, encoding, received_data);
     }
 
     fn handle_call(_src: InstanceId, _encoded_message: &[u8]) -> CallRet {
@@ -200,24 +77,43 @@ impl EdgeFunction for ClassifyFun {
         CallRet::NoReply
     }
 
- fn handle_init(_payload: Option<&[u8]>, _init_state: Option<&[u8]>) {
+ fn handle_init(payload: Option<&[u8]>, init_state: Option<&[u8]>) {
         edgeless_function::init_logger();
 
-        if let Some(result) = call_wrapper("SELECT id, metadata FROM WorkflowState LIMIT 1",) {
-            let classifier_base64 = result.metadata.classifier_base64;
+        let arguments = if let Some(payload) = payload {
+            let str_payload = core::str::from_utf8(payload).unwrap();
+            edgeless_function::parse_init_payload(str_payload)
+        } else {
+            std::collections::HashMap::new()
+        };
 
-            let serialized_model = base64::decode(&classifier_base64).unwrap();
+        let model_check_interval_ms = arguments.get("model_check_interval_ms").unwrap_or(&"0").parse::<u64>().expect("Invalid model check interval provided");
+        let confidence_threshold = arguments.get("confidence_threshold").unwrap_or(&"0").parse::<f64>().expect("Invalid confidence threshold provided");
+        let selector = model::ModelSelector::from_arguments(&arguments);
+        let hop_timing = hops::HopTiming::from_arguments(&arguments);
 
-            let deserialized_model: SerializableModel = bincode::deserialize(&serialized_model).unwrap();
+        // Batches buffered before a migration are classified as soon as the model is installed
+        let _ = LOADING.set(Mutex::new(model::Loading::new(&arguments, init_state)));
+        let _ = STATE.set(Mutex::new(None));
 
-            let _ = STATE.set(Mutex::new(
-                State { 
-                    classifier: deserialized_model.classifier 
-                }
-            ));
+        let _ = INIT_STATE.set(InitState { model: selector, model_check_interval_ms, confidence_threshold, hop_timing });
+        let selector = &INIT_STATE.get().unwrap().model;
+
+        let mut state = STATE.get().unwrap().lock().unwrap();
+        match model::Model::load(selector) {
+            Ok(model) => {
+                log::info!("Started, retrieved {} classifier version {:?}, saved it in the function's state", model.kind().name(), model.version);
+                install(&mut state, model);
+            }
+            Err(e) => {
+                selector.report_error(&format!("Started without a classifier, batches are buffered until one is retrieved: {}", e));
+                schedule_retry();
+            }
         }
 
-        log::info!("Started, retrieved Random Forest classifier, saved it in the function's state");
+        if model_check_interval_ms > 0 {
+            delayed_cast(model_check_interval_ms, "self", &wire::encode_control(CHECK_MODEL_MESSAGE));          // Action happens in handle_cast()
+        }
     }
 
     fn handle_stop() {
@@ -226,3 +122,142 @@ impl EdgeFunction for ClassifyFun {
 }
 
 edgeless_function::export!(ClassifyFun);
+
+fn decode_features(encoded_message: &[u8]) -> Option<(wire::Encoding, FeaturesPayload)> {
+    match wire::decode(encoded_message) {
+        Ok(parsed_received_data) => Some(parsed_received_data),
+        Err(err) => {
+            log::info!("Failed to deserialize message: {}", err);
+            None
+        }
+    }
+}
+
+// Classifies a feature payload and forwards the result to "classification_result"
+fn classify(model: &model::Model, received_us: Option<u64>, encoding: wire::Encoding, received_data: FeaturesPayload) {
+    let window_id = received_data.window_id();
+    let mut hops = received_data.hops;
+    let device_id = received_data.device_id;
+    let true_class = received_data.true_class;
+
+    if let Err(e) = model.accepts(received_data.schema_hash) {
+        log::info!("Window #{}: {}, skipping it", window_id, e);
+        return;
+    }
+
+    // Features in the column order used by classifier_training
+    let features_vec = activity_schema::feature_vector(
+        &received_data.features,
+        received_data.extended_features.as_ref(),
+        received_data.spectral_features.as_ref(),
+    );
+
+    let init_state = INIT_STATE.get().unwrap();
+    let confidence_threshold = init_state.confidence_threshold;
+    let prediction = match model.predict(features_vec, confidence_threshold) {
+        Ok(prediction) => prediction,
+        Err(e) => {
+            log::info!("{}", e);
+            return;
+        }
+    };
+
+    init_state.hop_timing.stamp(&mut hops, "classify", received_us);
+
+    let payload = ClassificationPayload {
+        batch_id: window_id.batch_id,
+        window_index: window_id.window_index,
+        device_id,
+        true_class,
+        classification: prediction.classification,
+        model_version: model.version,
+        class_votes: Some(prediction.class_votes),
+        hops,
+    };
+
+    // Forwarded with the encoding the features have been received with
+    let serialized_classification_result = match wire::encode(encoding, payload) {
+        Ok(message) => message,
+        Err(e) => {
+            log::info!("Error serializing classification result: {}", e);
+            Vec::new()
+        }
+    };
+
+    init_state.hop_timing.mark(window_id, "classify", HopEvent::Sent);
+    cast("classification_result", &serialized_classification_result);
+}
+
+// Retrieves the selected model if its version differs from the one in use, and swaps
+// it in under the state lock: a batch is classified either by the previous model or by the new one
+fn reload_model() {
+    let selector = &INIT_STATE.get().unwrap().model;
+    let mut state = STATE.get().unwrap().lock().unwrap();
+    let current_version = state.as_ref().and_then(|model| model.version);
+
+    let reloaded = match state.as_ref() {
+        Some(model) => model.reload(selector),
+        None => model::Model::load(selector).map(Some),
+    };
+
+    match reloaded {
+        Ok(Some(model)) => {
+            log::info!("Installed {} classifier version {:?} in place of version {:?}", model.kind().name(), model.version, current_version);
+            install(&mut state, model);
+        }
+        Ok(None) => {}
+        Err(e) => selector.report_error(&format!("Classifier not reloaded, keeping version {:?}: {}", current_version, e)),
+    }
+}
+
+// Retrieves the selected model while none is available, then retries after a longer delay if it could not.
+// Retries stop once a model is installed, be it by a retry or by a model check
+fn retry_model() {
+    let selector = &INIT_STATE.get().unwrap().model;
+    let mut state = STATE.get().unwrap().lock().unwrap();
+    if state.is_some() {
+        return;
+    }
+
+    match model::Model::load(selector) {
+        Ok(model) => {
+            log::info!("Retrieved {} classifier version {:?}", model.kind().name(), model.version);
+            install(&mut state, model);
+        }
+        Err(e) => {
+            selector.report_error(&format!("Classifier still not retrieved, batches are buffered: {}", e));
+            schedule_retry();
+        }
+    }
+}
+
+fn schedule_retry() {
+    let selector = &INIT_STATE.get().unwrap().model;
+    let mut loading = LOADING.get().unwrap().lock().unwrap();
+    let retry_in_ms = loading.next_retry();
+    loading.report_status(selector, selector.model_version, Some(retry_in_ms));
+    delayed_cast(retry_in_ms, "self", &wire::encode_control(RETRY_MODEL_MESSAGE));             // Action happens in handle_cast()
+}
+
+// Puts a model in use and classifies the batches buffered while none was available, in their order of arrival
+fn install(state: &mut Option<model::Model>, model: model::Model) {
+    let was_available = state.is_some();
+    let model = state.insert(model);
+    if was_available {
+        return;
+    }
+
+    let mut loading = LOADING.get().unwrap().lock().unwrap();
+    let buffered = loading.take_buffered();
+    loading.report_status(&INIT_STATE.get().unwrap().model, model.version, None);
+    drop(loading);
+
+    if !buffered.is_empty() {
+        log::info!("Classifying {} buffered batches", buffered.len());
+    }
+    for message in buffered {
+        if let Some((encoding, received_data)) = decode_features(&message.message) {
+            classify(model, message.received_us, encoding, received_data);
+        }
+    }
+}
@@ -1,70 +1,22 @@
 use edgeless_function::*;
-use serde::{Serialize, Deserialize};
-use serde_json;
-use statrs::statistics::{OrderStatistics, Statistics};
+use activity_schema::timing::HopEvent;
+use activity_schema::wire;
+use activity_schema::{FeaturesPayload, HopTimestamps, SamplesPayload, WindowId};
+use activity_pipeline::{extraction, hops};
 
 struct ExtractFeaturesFun;
 
-#[derive(Debug, Deserialize)]
-struct AccelerometerData {
-    x: f64,
-    y: f64,
-    z: f64,
-}
-
-#[derive(Debug, Serialize)]
-struct Features {
-    mean_mag: f64,
-    std_dev_mag: f64,
-    min_mag: f64,
-    max_mag: f64,
-    coeff_var_mag: f64,
-    percentile_25_mag: f64,
-    percentile_75_mag: f64,
-
-    mean_x: f64,
-    std_dev_x: f64,
-    min_x: f64,
-    max_x: f64,
-    coeff_var_x: f64,
-    percentile_25_x: f64,
-    percentile_75_x: f64,
-
-    mean_y: f64,
-    std_dev_y: f64,
-    min_y: f64,
-    max_y: f64,
-    coeff_var_y: f64,
-    percentile_25_y: f64,
-    percentile_75_y: f64,
-
-    mean_z: f64,
-    std_dev_z: f64,
-    min_z: f64,
-    max_z: f64,
-    coeff_var_z: f64,
-    percentile_25_z: f64,
-    percentile_75_z: f64,
-}
-
-#[derive(Debug, Deserialize)]
-struct ReceivedPayload {
-    batch_id: u64,
-    batch: Vec<AccelerometerData>,
-}
-
-#[derive(Debug, Serialize)]
-struct FeaturesPayload {
-    batch_id: u64,
-    features: Features,
-}
+static EXTRACTION: std::sync::OnceLock<std::sync::Mutex<extraction::Extraction>> = std::sync::OnceLock::new();
+static HOP_TIMING: std::sync::OnceLock<hops::HopTiming> = std::sync::OnceLock::new();
 
 impl EdgeFunction for ExtractFeaturesFun {
 
     // ------ EDGELESS FUNCTIONS REDEFINITION ------
     fn handle_cast(_src: InstanceId, encoded_message: &[u8]) {
-        let str_message = core::str::from_utf8(encoded_message).unwrap();
-        let received_data: ReceivedPayload = match serde_json::from_str(str_message) {
+        let hop_timing = HOP_TIMING.get().unwrap();
+        let received_us = hop_timing.received_us();
+        let mut extraction = EXTRACTION.get().unwrap().lock().unwrap();
+        let (encoding, received_data): (wire::Encoding, SamplesPayload) = match wire::decode(encoded_message) {
             Ok(parsed_received_data) => parsed_received_data,
             Err(err) => {
                 log::info!("Failed to deserialize message: {}", err);
@@ -73,106 +25,28 @@ impl EdgeFunction for ExtractFeaturesFun {
         };
 
         let batch_id = received_data.batch_id;
-        let accelerometer_data = received_data.batch;
-
-        // --------- Feature extraction ---------
-        // Calculate magnitudes for each sample
-        let mut magnitudes: Vec<f64> = accelerometer_data
-            .iter()
-            .map(|data| {
-                ((data.x.powi(2)) + (data.y.powi(2)) + (data.z.powi(2))).sqrt()
-            })
-            .collect();
-
-        // Calculate statistics for Magnitudes
-        let mean_mag = magnitudes.clone().mean();
-        let std_dev_mag = magnitudes.clone().std_dev();
-        let min_mag = magnitudes.iter().cloned().fold(f64::INFINITY, f64::min);
-        let max_mag = magnitudes.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
-        let mut coeff_var_mag = 0.0;
-        if mean_mag != 0.0 {
-            coeff_var_mag = std_dev_mag / mean_mag;
-        }
-        let percentile_25_mag = magnitudes.percentile(25);
-        let percentile_75_mag = magnitudes.percentile(75);
-        
-        // Extract x, y, z values for individual axis
-        let mut x_vals: Vec<f64> = accelerometer_data.iter().map(|data| data.x).collect();
-        let mut y_vals: Vec<f64> = accelerometer_data.iter().map(|data| data.y).collect();
-        let mut z_vals: Vec<f64> = accelerometer_data.iter().map(|data| data.z).collect();
-
-        // Function to compute statistics for an axis
-        fn compute_stats(axis_data: &mut Vec<f64>) -> (f64, f64, f64, f64, f64, f64, f64) {
-            let mean = axis_data.mean();
-            let std_dev = axis_data.std_dev();
-            let min = axis_data.iter().cloned().fold(f64::INFINITY, f64::min);
-            let max = axis_data.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
-            let mut coeff_var = 0.0;
-            if mean != 0.0 {
-                coeff_var = std_dev / mean;
-            }
-            let percentile_25 = axis_data.percentile(25);
-            let percentile_75 = axis_data.percentile(75);
-
-            (mean, std_dev, min, max, coeff_var, percentile_25, percentile_75)
+        hop_timing.mark(batch_id, "extract_features", HopEvent::Received);
+        if !received_data.faults.is_empty() {
+            log::info!("Batch #{} carries injected faults: {:?}", batch_id, received_data.faults);
         }
-    
-        // Compute statistics for X, Y, Z axes
-        let (mean_x, std_dev_x, min_x, max_x, coeff_var_x, percentile_25_x, percentile_75_x) = compute_stats(&mut x_vals);
-        let (mean_y, std_dev_y, min_y, max_y, coeff_var_y, percentile_25_y, percentile_75_y) = compute_stats(&mut y_vals);
-        let (mean_z, std_dev_z, min_z, max_z, coeff_var_z, percentile_25_z, percentile_75_z) = compute_stats(&mut z_vals);
-    
-        // Store all the features
-        let features = Features {
-            mean_mag,
-            std_dev_mag,
-            min_mag,
-            max_mag,
-            coeff_var_mag,
-            percentile_25_mag,
-            percentile_75_mag,
-
-            mean_x,
-            std_dev_x,
-            min_x,
-            max_x,
-            coeff_var_x,
-            percentile_25_x,
-            percentile_75_x,
-    
-            mean_y,
-            std_dev_y,
-            min_y,
-            max_y,
-            coeff_var_y,
-            percentile_25_y,
-            percentile_75_y,
-    
-            mean_z,
-            std_dev_z,
-            min_z,
-            max_z,
-            coeff_var_z,
-            percentile_25_z,
-            percentile_75_z,
-        };
+        let sequences = received_data.sequences();
+        let hops = received_data.hops;
+        let device_id = received_data.device_id;
 
-        log::info!("Features have been extracted.");
+        let windows = extraction.push(device_id, received_data.true_class, received_data.batch, sequences.as_deref(), received_data.overlap);
 
-        let payload = FeaturesPayload {
-            batch_id,
-            features,
-        };
-
-        let serialized_features = match serde_json::to_string(&payload) {
-            Ok(json) => json,
-            Err(e) => {
-                log::info!("Error serializing extracted features: {}", e);
-                String::new()
-            }
-        };
+        if windows.is_empty() {
+            extraction.report_partial(batch_id, device_id);
+            return;
+        }
 
-        cast("extracted_features", serialized_features.as_bytes());
+        // Features of every complete window, identified by the batch that completed it and its index
+        let schema_hash = extraction.schema_hash();
+        for (window_index, window) in windows.into_iter().enumerate() {
+            log::info!("Features have been extracted.");
+            let window_id = WindowId { batch_id, window_index: window_index as u32 };
+            send_features(encoding, window_id, device_id, schema_hash, window, hops.clone(), received_us);
+        }
     }
 
     fn handle_call(_src: InstanceId, _encoded_message: &[u8]) -> CallRet {
@@ -180,10 +54,21 @@ impl EdgeFunction for ExtractFeaturesFun {
         CallRet::NoReply
     }
 
-    fn handle_init(_payload: Option<&[u8]>, _init_state: Option<&[u8]>) {
+    fn handle_init(payload: Option<&[u8]>, _init_state: Option<&[u8]>) {
         edgeless_function::init_logger();
-        log::info!("Started");
 
+        let arguments = if let Some(payload) = payload {
+            let str_payload = core::str::from_utf8(payload).unwrap();
+            edgeless_function::parse_init_payload(str_payload)
+        } else {
+            std::collections::HashMap::new()
+        };
+
+        let config = extraction::ExtractionConfig::from_arguments(&arguments);
+        log::info!("Started ({})", config.describe());
+
+        let _ = EXTRACTION.set(std::sync::Mutex::new(extraction::Extraction::new(config)));
+        let _ = HOP_TIMING.set(hops::HopTiming::from_arguments(&arguments));
     }
 
     fn handle_stop() {
@@ -191,4 +76,41 @@ impl EdgeFunction for ExtractFeaturesFun {
     }
 }
 
-edgeless_function::export!(ExtractFeaturesFun);
\ No newline at end of file
+edgeless_function::export!(ExtractFeaturesFun);
+
+// Forwards the features of a complete window, with the encoding the samples have been received with
+fn send_features(
+    encoding: wire::Encoding,
+    window_id: WindowId,
+    device_id: u32,
+    schema_hash: u64,
+    window: extraction::WindowFeatures,
+    mut hops: Vec<HopTimestamps>,
+    received_us: Option<u64>,
+) {
+    let hop_timing = HOP_TIMING.get().unwrap();
+    hop_timing.stamp(&mut hops, "extract_features", received_us);
+
+    let payload = FeaturesPayload {
+        batch_id: window_id.batch_id,
+        window_index: window_id.window_index,
+        device_id,
+        true_class: window.true_class,
+        features: window.features,
+        extended_features: window.extended_features,
+        spectral_features: window.spectral_features,
+        schema_hash,
+        hops,
+    };
+
+    let serialized_features = match wire::encode(encoding, payload) {
+        Ok(message) => message,
+        Err(e) => {
+            log::info!("Error serializing extracted features: {}", e);
+            Vec::new()
+        }
+    };
+
+    hop_timing.mark(window_id, "extract_features", HopEvent::Sent);
+    cast("extracted_features", &serialized_features);
+}
@@ -0,0 +1,313 @@
+use edgeless_function::*;
+use activity_schema::timing::HopEvent;
+use activity_schema::wire;
+use activity_schema::FeaturesPayload;
+use activity_pipeline::{extraction, generator, hops, model, report};
+
+// Whole activity recognition pipeline in a single function, to compare the TTC and the energy of a
+// monolithic deployment against the split one (generate_samples -> extract_features -> classify ->
+// handle_class_result). The logic is that of the split functions, shared through activity_pipeline.
+// As classify, it retries a model it could not retrieve and buffers the features of the windows
+// meanwhile; its state sync carries both the generator and the buffered windows (see activity_pipeline::synced)
+
+struct FusedActivityRecognitionFun;
+
+// Model retrieval and checks, as in classify: control messages sent by the function to itself every
+// model_check_interval_ms or until the model could be retrieved, or cast by any function mapped to it
+const CHECK_MODEL_MESSAGE: &str = "check_model";
+const RELOAD_MODEL_MESSAGE: &str = "reload_model";
+const RETRY_MODEL_MESSAGE: &str = "retry_model";
+
+static MODEL_SELECTOR: std::sync::OnceLock<model::ModelSelector> = std::sync::OnceLock::new();
+static MODEL_CHECK_INTERVAL_MS: std::sync::OnceLock<u64> = std::sync::OnceLock::new();
+static CONFIDENCE_THRESHOLD: std::sync::OnceLock<f64> = std::sync::OnceLock::new();
+static GENERATOR: std::sync::OnceLock<generator::GeneratorConfig> = std::sync::OnceLock::new();
+static STATE: std::sync::OnceLock<std::sync::Mutex<generator::State>> = std::sync::OnceLock::new();
+static EXTRACTION: std::sync::OnceLock<std::sync::Mutex<extraction::Extraction>> = std::sync::OnceLock::new();
+static MODEL: std::sync::OnceLock<std::sync::Mutex<Option<model::Model>>> = std::sync::OnceLock::new();        // None until a model could be retrieved
+static LOADING: std::sync::OnceLock<std::sync::Mutex<model::Loading>> = std::sync::OnceLock::new();           // Locked after MODEL, when both are
+static SCOREBOARD: std::sync::OnceLock<std::sync::Mutex<report::Scoreboard>> = std::sync::OnceLock::new();
+static HOP_TIMING: std::sync::OnceLock<hops::HopTiming> = std::sync::OnceLock::new();
+
+impl EdgeFunction for FusedActivityRecognitionFun {
+
+    // ------ EDGELESS FUNCTIONS REDEFINITION ------
+    fn handle_cast(_src: InstanceId, encoded_message: &[u8]) {
+        let hop_timing = HOP_TIMING.get().unwrap();
+        let received_us = hop_timing.received_us();
+
+        match wire::decode_control(encoded_message) {
+            Some(RETRY_MODEL_MESSAGE) => {
+                retry_model();
+                return;
+            }
+            Some(control @ (CHECK_MODEL_MESSAGE | RELOAD_MODEL_MESSAGE)) => {
+                reload_model();
+                let model_check_interval_ms = *MODEL_CHECK_INTERVAL_MS.get().unwrap();
+                if control == CHECK_MODEL_MESSAGE && model_check_interval_ms > 0 {
+                    delayed_cast(model_check_interval_ms, "self", &wire::encode_control(CHECK_MODEL_MESSAGE));
+                }
+                return;
+            }
+            Some(control) => {
+                log::info!("Unknown control message: {}", control);
+                return;
+            }
+            None => {}
+        }
+
+        let mut state = STATE.get().unwrap().lock().unwrap();
+
+        // Each device schedules itself with its id as message
+        let device_id = core::str::from_utf8(encoded_message).unwrap_or("").parse::<usize>().unwrap_or(0);
+
+        generator::generate(GENERATOR.get().unwrap(), &mut state, device_id, |batch| {
+            // The stages run within this call, marked in "hop_log" as a whole
+            let batch_id = batch.batch_id;
+            hop_timing.mark(batch_id, "fused_activity_recognition", HopEvent::Received);
+            run_pipeline(hop_timing, received_us, batch);
+            hop_timing.mark(batch_id, "fused_activity_recognition", HopEvent::Sent);
+        });
+    }
+
+    fn handle_call(_src: InstanceId, _encoded_message: &[u8]) -> CallRet {
+        log::info!("handle_call() called");
+        CallRet::NoReply
+    }
+
+    fn handle_init(payload: Option<&[u8]>, init_state: Option<&[u8]>) {
+        edgeless_function::init_logger();
+
+        let arguments = if let Some(payload) = payload {
+            let str_payload = core::str::from_utf8(payload).unwrap();
+            edgeless_function::parse_init_payload(str_payload)
+        } else {
+            std::collections::HashMap::new()
+        };
+
+        // Same keys as generate_samples and extract_features
+        let config = extraction::ExtractionConfig::from_arguments(&arguments);
+        log::info!("Started ({})", config.describe());
+        let _ = EXTRACTION.set(std::sync::Mutex::new(extraction::Extraction::new(config)));
+
+        let model_check_interval_ms = arguments.get("model_check_interval_ms").unwrap_or(&"0").parse::<u64>().expect("Invalid model check interval provided");
+        let _ = MODEL_CHECK_INTERVAL_MS.set(model_check_interval_ms);
+        let confidence_threshold = arguments.get("confidence_threshold").unwrap_or(&"0").parse::<f64>().expect("Invalid confidence threshold provided");
+        let _ = CONFIDENCE_THRESHOLD.set(confidence_threshold);
+
+        let _ = SCOREBOARD.set(std::sync::Mutex::new(report::Scoreboard::new()));
+        let _ = HOP_TIMING.set(hops::HopTiming::from_arguments(&arguments));
+
+        // Windows buffered before a migration are classified as soon as the model is installed
+        let _ = LOADING.set(std::sync::Mutex::new(model::Loading::new(&arguments, init_state)));
+        let _ = MODEL.set(std::sync::Mutex::new(None));
+        let _ = MODEL_SELECTOR.set(model::ModelSelector::from_arguments(&arguments));
+        let selector = MODEL_SELECTOR.get().unwrap();
+
+        let mut model = MODEL.get().unwrap().lock().unwrap();
+        match model::Model::load(selector) {
+            Ok(new_model) => {
+                log::info!("Retrieved {} classifier version {:?}, saved it in the function's state", new_model.kind().name(), new_model.version);
+                install(&mut model, new_model);
+            }
+            Err(e) => {
+                selector.report_error(&format!("Started without a classifier, windows are buffered until one is retrieved: {}", e));
+                schedule_retry();
+            }
+        }
+        drop(model);
+
+        if model_check_interval_ms > 0 {
+            delayed_cast(model_check_interval_ms, "self", &wire::encode_control(CHECK_MODEL_MESSAGE));
+        }
+
+        // Schedules the devices last, the rest of the pipeline being ready for their first batches
+        let (generator, state) = generator::init(&arguments, init_state);
+        let _ = GENERATOR.set(generator);
+        let _ = STATE.set(std::sync::Mutex::new(state));
+    }
+
+    fn handle_stop() {
+        log::info!("Stopped");
+    }
+}
+
+edgeless_function::export!(FusedActivityRecognitionFun);
+
+// Stages follow each other within this call: they are stamped as the split functions do,
+// the network time between them being that of handing the data over in memory
+fn run_pipeline(hop_timing: &hops::HopTiming, received_us: Option<u64>, batch: generator::Batch) {
+    let mut hops = Vec::new();
+    hop_timing.stamp(&mut hops, "generate_samples", received_us);
+
+    // --------- Feature extraction ---------
+    let extract_received_us = hop_timing.received_us();
+    if !batch.faults.is_empty() {
+        log::info!("Batch #{} carries injected faults: {:?}", batch.batch_id, batch.faults);
+    }
+
+    let mut extraction = EXTRACTION.get().unwrap().lock().unwrap();
+    let sequences = activity_schema::sample_sequences(batch.first_sequence, &batch.dropped, batch.samples.len());
+    let windows = extraction.push(batch.device_id, batch.true_class, batch.samples, Some(&sequences), batch.overlap);
+    if windows.is_empty() {
+        extraction.report_partial(batch.batch_id, batch.device_id);
+        return;
+    }
+    log::info!("Features have been extracted.");
+    let schema_hash = extraction.schema_hash();
+    drop(extraction);
+
+    hop_timing.stamp(&mut hops, "extract_features", extract_received_us);
+
+    // --------- Classification, scoring and reporting of every complete window ---------
+    let model = MODEL.get().unwrap().lock().unwrap();
+    for (window_index, window) in windows.into_iter().enumerate() {
+        let classify_received_us = hop_timing.received_us();
+        let features = FeaturesPayload {
+            batch_id: batch.batch_id,
+            window_index: window_index as u32,
+            device_id: batch.device_id,
+            true_class: window.true_class,
+            features: window.features,
+            extended_features: window.extended_features,
+            spectral_features: window.spectral_features,
+            schema_hash,
+            hops: hops.clone(),
+        };
+
+        match model.as_ref() {
+            Some(model) => classify(model, classify_received_us, features),
+            None => buffer(classify_received_us, features),
+        }
+    }
+}
+
+// Keeps the features of a window until a model is retrieved, in the payload classify would receive
+fn buffer(received_us: Option<u64>, features: FeaturesPayload) {
+    match wire::encode(wire::Encoding::Bincode, features) {
+        Ok(message) => LOADING.get().unwrap().lock().unwrap().buffer(received_us, &message),
+        Err(e) => log::info!("Error serializing the features of a window to buffer: {}", e),
+    }
+}
+
+// Classifies the features of a window, then scores and reports the classification as handle_class_result does
+fn classify(model: &model::Model, received_us: Option<u64>, features: FeaturesPayload) {
+    let hop_timing = HOP_TIMING.get().unwrap();
+    let window_id = features.window_id();
+    if let Err(e) = model.accepts(features.schema_hash) {
+        log::info!("Window #{}: {}, skipping it", window_id, e);
+        return;
+    }
+
+    // Features in the column order used by classifier_training
+    let features_vec = activity_schema::feature_vector(
+        &features.features,
+        features.extended_features.as_ref(),
+        features.spectral_features.as_ref(),
+    );
+
+    let prediction = match model.predict(features_vec, *CONFIDENCE_THRESHOLD.get().unwrap()) {
+        Ok(prediction) => prediction,
+        Err(e) => {
+            log::info!("{}", e);
+            return;
+        }
+    };
+
+    let mut hops = features.hops;
+    hop_timing.stamp(&mut hops, "classify", received_us);
+
+    let handle_received_us = hop_timing.received_us();
+    log::info!("{}", report::display_class_result(&prediction.classification));
+    cast("ttc_measurement_end", format!("{}", window_id).as_bytes());
+
+    SCOREBOARD.get().unwrap().lock().unwrap().score(
+        window_id,
+        features.device_id,
+        features.true_class,
+        prediction.classification,
+        model.version,
+        Some(prediction.class_votes),
+    );
+
+    hop_timing.stamp(&mut hops, "handle_class_result", handle_received_us);
+
+    // No payload is serialized between the stages
+    if !hops.is_empty() {
+        report::report_latency(window_id, features.device_id, None, &hops);
+    }
+}
+
+// Swaps in the selected model if its version differs from the one in use, as classify does
+fn reload_model() {
+    let selector = MODEL_SELECTOR.get().unwrap();
+    let mut model = MODEL.get().unwrap().lock().unwrap();
+    let current_version = model.as_ref().and_then(|model| model.version);
+
+    let reloaded = match model.as_ref() {
+        Some(model) => model.reload(selector),
+        None => model::Model::load(selector).map(Some),
+    };
+
+    match reloaded {
+        Ok(Some(new_model)) => {
+            log::info!("Installed {} classifier version {:?} in place of version {:?}", new_model.kind().name(), new_model.version, current_version);
+            install(&mut model, new_model);
+        }
+        Ok(None) => {}
+        Err(e) => selector.report_error(&format!("Classifier not reloaded, keeping version {:?}: {}", current_version, e)),
+    }
+}
+
+// Retrieves the selected model while none is available, then retries after a longer delay if it could not,
+// as classify does
+fn retry_model() {
+    let selector = MODEL_SELECTOR.get().unwrap();
+    let mut model = MODEL.get().unwrap().lock().unwrap();
+    if model.is_some() {
+        return;
+    }
+
+    match model::Model::load(selector) {
+        Ok(new_model) => {
+            log::info!("Retrieved {} classifier version {:?}", new_model.kind().name(), new_model.version);
+            install(&mut model, new_model);
+        }
+        Err(e) => {
+            selector.report_error(&format!("Classifier still not retrieved, windows are buffered: {}", e));
+            schedule_retry();
+        }
+    }
+}
+
+fn schedule_retry() {
+    let selector = MODEL_SELECTOR.get().unwrap();
+    let mut loading = LOADING.get().unwrap().lock().unwrap();
+    let retry_in_ms = loading.next_retry();
+    loading.report_status(selector, selector.model_version, Some(retry_in_ms));
+    delayed_cast(retry_in_ms, "self", &wire::encode_control(RETRY_MODEL_MESSAGE));
+}
+
+// Puts a model in use and classifies the windows buffered while none was available, in their order of arrival
+fn install(state: &mut Option<model::Model>, new_model: model::Model) {
+    let was_available = state.is_some();
+    let model = state.insert(new_model);
+    if was_available {
+        return;
+    }
+
+    let mut loading = LOADING.get().unwrap().lock().unwrap();
+    let buffered = loading.take_buffered();
+    loading.report_status(MODEL_SELECTOR.get().unwrap(), model.version, None);
+    drop(loading);
+
+    if !buffered.is_empty() {
+        log::info!("Classifying {} buffered windows", buffered.len());
+    }
+    for message in buffered {
+        match wire::decode::<FeaturesPayload>(&message.message) {
+            Ok((_, features)) => classify(model, message.received_us, features),
+            Err(e) => log::info!("Failed to deserialize a buffered window: {}", e),
+        }
+    }
+}
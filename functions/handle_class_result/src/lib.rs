@@ -1,40 +1,22 @@
 use edgeless_function::*;
-use serde::{Deserialize};
-use serde_json;
+use activity_schema::timing::HopEvent;
+use activity_schema::wire;
+use activity_schema::ClassificationPayload;
+use activity_pipeline::{hops, report};
 
 struct HandleClassResultFun;
 
-#[derive(Debug, Deserialize)]
-enum Classification {
-    Jogging,
-    Walking,
-    Standing,
-    Stairs,
-    Sitting,
-}
-
-#[derive(Debug, Deserialize)]
-struct ClassificationPayload {
-    batch_id: u64,
-    classification: Classification,
-}
+static STATE: std::sync::OnceLock<std::sync::Mutex<report::Scoreboard>> = std::sync::OnceLock::new();
+static HOP_TIMING: std::sync::OnceLock<hops::HopTiming> = std::sync::OnceLock::new();
 
 impl EdgeFunction for HandleClassResultFun {
 
     // ------ EDGELESS FUNCTIONS REDEFINITION ------
     fn handle_cast(_src: InstanceId, encoded_message: &[u8]) {
-        let display_class_result = |result: &Classification| -> String {
-            match result {
-                Classification::Jogging => "'JOGGING' activity detected".to_string(),
-                Classification::Walking => "'WALKING' activity detected".to_string(),
-                Classification::Standing => "'STANDING' activity detected".to_string(),
-                Classification::Stairs => "'STAIRS' activity detected".to_string(),
-                Classification::Sitting => "'SITTING' activity detected".to_string(),
-            }
-        };
+        let hop_timing = HOP_TIMING.get().unwrap();
+        let received_us = hop_timing.received_us();
 
-        let str_message = core::str::from_utf8(encoded_message).unwrap();
-        let class_result: ClassificationPayload = match serde_json::from_str(str_message) {
+        let (encoding, class_result): (wire::Encoding, ClassificationPayload) = match wire::decode(encoded_message) {
             Ok(parsed_class_result) => parsed_class_result,
             Err(err) => {
                 log::info!("Failed to deserialize message: {}", err);
@@ -42,10 +24,31 @@ impl EdgeFunction for HandleClassResultFun {
             }
         };
 
-        log::info!("{}", display_class_result(&class_result.classification));
+        let window_id = class_result.window_id();
+        hop_timing.mark(window_id, "handle_class_result", HopEvent::Received);
+
+        log::info!("{}", report::display_class_result(&class_result.classification));
+
+        cast("ttc_measurement_end", format!("{}", window_id).as_bytes());
 
-        let batch_id = class_result.batch_id;
-        cast("ttc_measurement_end", format!("{}", batch_id).as_bytes());
+        // --------- Online scoring against the ground truth ---------
+        STATE.get().unwrap().lock().unwrap().score(
+            window_id,
+            class_result.device_id,
+            class_result.true_class,
+            class_result.classification,
+            class_result.model_version,
+            class_result.class_votes,
+        );
+
+        // --------- Per-hop latency breakdown, from the in-band stamps where the nodes have a clock ---------
+        let mut hops = class_result.hops;
+        hop_timing.stamp(&mut hops, "handle_class_result", received_us);
+        hop_timing.mark(window_id, "handle_class_result", HopEvent::Sent);
+
+        if !hops.is_empty() {
+            report::report_latency(window_id, class_result.device_id, Some(format!("{:?}", encoding)), &hops);
+        }
     }
 
     fn handle_call(_src: InstanceId, _encoded_message: &[u8]) -> CallRet {
@@ -53,8 +56,19 @@ impl EdgeFunction for HandleClassResultFun {
         CallRet::NoReply
     }
 
- fn handle_init(_payload: Option<&[u8]>, _init_state: Option<&[u8]>) {
+ fn handle_init(payload: Option<&[u8]>, _init_state: Option<&[u8]>) {
         edgeless_function::init_logger();
+
+        let arguments = if let Some(payload) = payload {
+            let str_payload = core::str::from_utf8(payload).unwrap();
+            edgeless_function::parse_init_payload(str_payload)
+        } else {
+            std::collections::HashMap::new()
+        };
+
+        let _ = STATE.set(std::sync::Mutex::new(report::Scoreboard::new()));
+        let _ = HOP_TIMING.set(hops::HopTiming::from_arguments(&arguments));
+
         log::info!("Started");
     }
 
@@ -2,6 +2,7 @@ use edgeless_function::*;
 use smartcore::linalg::basic::matrix::DenseMatrix;
 use smartcore::ensemble::random_forest_classifier::RandomForestClassifier;
 use std::sync::{Mutex, OnceLock};
+use activity_schema::{Classification, Features};
 use serde::{Serialize, Deserialize};
 use bincode;
 
@@ -30,56 +31,12 @@ struct SerializableModel {
     classifier: RandomForestClassifier<f64, i32, DenseMatrix<f64>, Vec<i32>>,
 }
 
-#[derive(Debug, Deserialize)]
-struct Features {
-    mean_mag: f64,
-    std_dev_mag: f64,
-    min_mag: f64,
-    max_mag: f64,
-    coeff_var_mag: f64,
-    percentile_25_mag: f64,
-    percentile_75_mag: f64,
-
-    mean_x: f64,
-    std_dev_x: f64,
-    min_x: f64,
-    max_x: f64,
-    coeff_var_x: f64,
-    percentile_25_x: f64,
-    percentile_75_x: f64,
-
-    mean_y: f64,
-    std_dev_y: f64,
-    min_y: f64,
-    max_y: f64,
-    coeff_var_y: f64,
-    percentile_25_y: f64,
-    percentile_75_y: f64,
-
-    mean_z: f64,
-    std_dev_z: f64,
-    min_z: f64,
-    max_z: f64,
-    coeff_var_z: f64,
-    percentile_25_z: f64,
-    percentile_75_z: f64,
-}
-
 #[derive(Debug, Deserialize)]
 struct ReceivedPayload {
     batch_id: u64,
     features: Features,
 }
 
-#[derive(Debug, Serialize)]
-enum Classification {
-    Jogging,
-    Walking,
-    Standing,
-    Stairs,
-    Sitting,
-}
-
 #[derive(Debug, Serialize)]
 struct ClassificationPayload {
     batch_id: u64,
@@ -127,57 +84,19 @@ impl EdgeFunction for ClassifyFun {
         let batch_id = received_data.batch_id;
         let extracted_features = received_data.features;
 
-        // Features struct into Vec<f64> array
-        let features_vec: Vec<f64> = vec![
-            extracted_features.mean_mag,
-            extracted_features.std_dev_mag,
-            extracted_features.min_mag,
-            extracted_features.max_mag,
-            extracted_features.coeff_var_mag,
-            extracted_features.percentile_25_mag,
-            extracted_features.percentile_75_mag,
-
-            extracted_features.mean_x,
-            extracted_features.std_dev_x,
-            extracted_features.min_x,
-            extracted_features.max_x,
-            extracted_features.coeff_var_x,
-            extracted_features.percentile_25_x,
-            extracted_features.percentile_75_x,
-
-            extracted_features.mean_y,
-            extracted_features.std_dev_y,
-            extracted_features.min_y,
-            extracted_features.max_y,
-            extracted_features.coeff_var_y,
-            extracted_features.percentile_25_y,
-            extracted_features.percentile_75_y,
-
-            extracted_features.mean_z,
-            extracted_features.std_dev_z,
-            extracted_features.min_z,
-            extracted_features.max_z,
-            extracted_features.coeff_var_z,
-            extracted_features.percentile_25_z,
-            extracted_features.percentile_75_z,
-        ];
+        let features_vec = activity_schema::feature_vector(&extracted_features, None, None);
 
         let sample = DenseMatrix::from_2d_vec(&vec![features_vec]);
 
         let prediction = state.classifier.predict(&sample).unwrap();
 
-        let classification_result;
-        if prediction[0] == 0 {
-            classification_result = Classification::Jogging;
-        } else if prediction[0] == 1 {
-            classification_result = Classification::Walking;
-        } else if prediction[0] == 2 {
-            classification_result = Classification::Standing;
-        } else if prediction[0] == 3 {
-            classification_result = Classification::Stairs;
-        } else {
-            classification_result = Classification::Sitting;
-        }
+        let classification_result = match u32::try_from(prediction[0]).ok().and_then(Classification::from_label) {
+            Some(class) => class,
+            None => {
+                log::info!("Classifier returned an unknown label: {}", prediction[0]);
+                return;
+            }
+        };
 
         log::info!("Classified the received features: {:?}", prediction);
 
@@ -1,4 +1,5 @@
 use edgeless_function::*;
+use activity_schema::{AccelerometerData, Features};
 use serde::{Serialize, Deserialize};
 use serde_json;
 use statrs::statistics::{OrderStatistics, Statistics};
@@ -9,48 +10,6 @@ struct State {
     telemetry_id: i32,            // Identifies each telemetry sample couple
 }
 
-#[derive(Debug, Deserialize)]
-struct AccelerometerData {
-    x: f64,
-    y: f64,
-    z: f64,
-}
-
-#[derive(Debug, Serialize)]
-struct Features {
-    mean_mag: f64,
-    std_dev_mag: f64,
-    min_mag: f64,
-    max_mag: f64,
-    coeff_var_mag: f64,
-    percentile_25_mag: f64,
-    percentile_75_mag: f64,
-
-    mean_x: f64,
-    std_dev_x: f64,
-    min_x: f64,
-    max_x: f64,
-    coeff_var_x: f64,
-    percentile_25_x: f64,
-    percentile_75_x: f64,
-
-    mean_y: f64,
-    std_dev_y: f64,
-    min_y: f64,
-    max_y: f64,
-    coeff_var_y: f64,
-    percentile_25_y: f64,
-    percentile_75_y: f64,
-
-    mean_z: f64,
-    std_dev_z: f64,
-    min_z: f64,
-    max_z: f64,
-    coeff_var_z: f64,
-    percentile_25_z: f64,
-    percentile_75_z: f64,
-}
-
 #[derive(Debug, Deserialize)]
 struct ReceivedPayload {
     batch_id: u64,
@@ -1,4 +1,5 @@
 use edgeless_function::*;
+use activity_schema::AccelerometerData;
 use serde::Serialize;
 use serde_json;
 
@@ -15,13 +16,6 @@ struct State {
     lcg: edgeless_function::lcg::Lcg,           // Random values generation
 }
 
-#[derive(Debug, Serialize)]
-struct AccelerometerData {
-    x: f64,
-    y: f64,
-    z: f64,
-}
-
 #[derive(Debug, Serialize)]
 struct Payload {
     batch_id: u64,
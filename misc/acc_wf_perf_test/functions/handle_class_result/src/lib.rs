@@ -1,4 +1,5 @@
 use edgeless_function::*;
+use activity_schema::Classification;
 use serde::{Deserialize};
 use serde_json;
 
@@ -8,15 +9,6 @@ struct State {
     telemetry_id: i32,      // Identifies each telemetry sample couple
 }
 
-#[derive(Debug, Deserialize)]
-enum Classification {
-    Jogging,
-    Walking,
-    Standing,
-    Stairs,
-    Sitting,
-}
-
 #[derive(Debug, Deserialize)]
 struct ClassificationPayload {
     batch_id: u64,
@@ -40,6 +32,7 @@ impl EdgeFunction for HandleClassResultFun {
                 Classification::Standing => "'STANDING' activity detected".to_string(),
                 Classification::Stairs => "'STAIRS' activity detected".to_string(),
                 Classification::Sitting => "'SITTING' activity detected".to_string(),
+                Classification::Unknown => "Uncertain activity, classification rejected".to_string(),
             }
         };
 